@@ -0,0 +1,100 @@
+#![feature(test)]
+
+extern crate test;
+
+use futures::executor::block_on;
+use test::Bencher;
+
+const JOBS: u64 = 1000;
+
+/// Spawns many tiny jobs up front, then awaits their handles in order.
+#[bench]
+fn spawn_then_await(b: &mut Bencher) {
+    b.iter(|| {
+        block_on(moro::infallible_scope!(|scope| {
+            let jobs: Vec<_> = (0..JOBS).map(|i| scope.spawn(async move { i })).collect();
+            let mut sum = 0;
+            for job in jobs {
+                sum += job.await.unwrap();
+            }
+            sum
+        }))
+    });
+}
+
+/// Spawns each job and immediately awaits it.
+#[bench]
+fn spawn_and_await_each(b: &mut Bencher) {
+    b.iter(|| {
+        block_on(moro::infallible_scope!(|scope| {
+            let mut sum = 0;
+            for i in 0..JOBS {
+                sum += scope.spawn(async move { i }).await.unwrap();
+            }
+            sum
+        }))
+    });
+}
+
+/// Spawns many jobs whose handles are dropped; the scope waits for them all.
+#[bench]
+fn spawn_detached(b: &mut Bencher) {
+    b.iter(|| {
+        block_on(moro::infallible_scope!(|scope| {
+            for i in 0..JOBS {
+                drop(scope.spawn(async move { test::black_box(i) }));
+            }
+        }))
+    });
+}
+
+/// Returns `Pending` once, waking itself, so that the scope polls the job again.
+async fn yield_now() {
+    let mut yielded = false;
+    futures::future::poll_fn(|cx| {
+        if yielded {
+            std::task::Poll::Ready(())
+        } else {
+            yielded = true;
+            cx.waker().wake_by_ref();
+            std::task::Poll::Pending
+        }
+    })
+    .await
+}
+
+/// Spawns a few jobs that yield many times, so that most of the time goes to the
+/// scope polling its jobs.
+#[bench]
+fn poll_yielding_jobs(b: &mut Bencher) {
+    b.iter(|| {
+        block_on(moro::infallible_scope!(|scope| {
+            for _ in 0..10 {
+                scope.spawn(async {
+                    for _ in 0..JOBS {
+                        yield_now().await;
+                    }
+                });
+            }
+        }))
+    });
+}
+
+/// Spawns ten jobs from within a job.
+async fn spawn_ten<'scope>(scope: &'scope moro::Scope<'scope, '_>, i: u64) {
+    for j in 0..10 {
+        drop(scope.spawn(async move { test::black_box(i + j) }));
+    }
+}
+
+/// Spawns jobs from within jobs while the scope is polling them.
+#[bench]
+fn spawn_nested(b: &mut Bencher) {
+    b.iter(|| {
+        block_on(moro::infallible_scope!(|scope| {
+            for i in 0..JOBS / 10 {
+                drop(scope.spawn(spawn_ten(&*scope, i)));
+            }
+        }))
+    });
+}
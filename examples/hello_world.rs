@@ -1,5 +1,3 @@
-#![feature(async_closure)]
-
 #[tokio::main]
 pub async fn main() {
     let value = 22;
@@ -9,11 +7,11 @@ pub async fn main() {
                 value // access stack values that outlive scope
             });
 
-            let v = future2.await * 2;
+            let v = future2.output().await * 2;
             v
         });
 
-        let v = future1.await * 2;
+        let v = future1.output().await * 2;
         v
     });
     let result = scope.await;
@@ -2,19 +2,32 @@
 async fn main() {
     eprintln!("all positive {:?}", run(&vec![1, 2, 10]).await);
     eprintln!("some negative {:?}", run(&vec![1, 2, -3, 10]).await);
+    eprintln!("all errors {:?}", run_all(&vec![1, -2, -3, 10]).await);
 }
 
 /// Run the simulated journal.
 pub async fn run(inputs: &Vec<i32>) -> anyhow::Result<()> {
     moro::async_scope!(|scope| {
         for input in inputs {
-            let _ = scope.spawn(validate(input)).or_cancel(scope);
+            drop(scope.spawn(validate(input)).or_cancel(scope));
         }
         Ok(())
     })
     .await
 }
 
+/// Like `run`, but validates every input and reports all of the errors.
+pub async fn run_all(inputs: &Vec<i32>) -> Result<(), moro::MultiError<anyhow::Error>> {
+    moro::async_scope!(|scope| {
+        for input in inputs {
+            drop(scope.spawn(validate(input)).or_cancel(scope));
+        }
+        Ok(())
+    })
+    .with_error_mode(moro::ErrorMode::CollectAll)
+    .await
+}
+
 pub async fn validate(input: &i32) -> anyhow::Result<()> {
     if *input < 0 {
         anyhow::bail!("input out of range: {input}");
@@ -34,3 +47,9 @@ async fn some_negative() {
         Ok(()) => panic!("expected an error"),
     }
 }
+
+#[tokio::test]
+async fn all_negative_reported() {
+    let errors = run_all(&vec![-1, 2, -3]).await.unwrap_err();
+    assert_eq!(errors.errors().len(), 2);
+}
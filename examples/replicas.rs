@@ -3,20 +3,19 @@
 // Using FuturesUnordered, easy to deadlock, as shown [here](https://play.rust-lang.org/?version=stable&mode=debug&edition=2021&gist=66455d3e61217d86ec4839c926619bf0).
 // Based on real-life bug.
 
-use tokio::sync::mpsc::channel;
-
 #[tokio::main]
 async fn main() {
     moro::async_scope!(|scope| {
         // Start up the replicas
         let replicas = 3;
-        let mut host_senders = vec![];
-        let mut host_futures = vec![];
-        for host in 0..replicas {
-            let (sender, receiver) = channel(222);
-            host_senders.push(sender);
-            host_futures.push(scope.spawn(replica(host, receiver)));
-        }
+        let (host_senders, host_receivers): (Vec<_>, Vec<_>) =
+            (0..replicas).map(|_| scope.channel(222)).unzip();
+        let hosts = scope.spawn_all(
+            host_receivers
+                .into_iter()
+                .zip(0..)
+                .map(|(receiver, host)| replica(host, receiver)),
+        );
 
         // Send the data
         for message in ['H', 'e', 'l', 'l', 'o', '\n'] {
@@ -25,9 +24,9 @@ async fn main() {
             }
         }
 
-        // Drain the replicas.
-        for future in host_futures {
-            let (host, count) = future.await;
+        // Drain the replicas. The senders stay open meanwhile, so a replica that read
+        // past the end of the message would get `RecvError::Stalled` rather than hang.
+        for (host, count) in hosts.await {
             eprintln!("Host {host} received {count} bytes.");
         }
     })
@@ -36,9 +35,9 @@ async fn main() {
     eprintln!("All done")
 }
 
-async fn replica(host: u32, mut receiver: tokio::sync::mpsc::Receiver<char>) -> (u32, usize) {
+async fn replica(host: u32, mut receiver: moro::Receiver<'_, char>) -> (u32, usize) {
     let mut count = 0;
-    while let Some(message) = receiver.recv().await {
+    while let Ok(message) = receiver.recv().await {
         eprintln!("Host {host} received message {message:?}");
         if message == '\n' {
             break;
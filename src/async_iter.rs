@@ -27,25 +27,350 @@ pub trait AsyncIterator {
             filter_op: op,
         }
     }
+
+    /// Applies `op` to each item. Like [`Iterator::map`], `op` is a plain closure, so
+    /// mapping doesn't cost a future per item; use [`then`](Self::then) when `op` needs to
+    /// await.
+    ///
+    /// ```rust
+    /// use moro::AsyncIterator;
+    ///
+    /// # futures::executor::block_on(async {
+    /// let mut iter = moro::source::range(1..3).map(|n| n * 10);
+    /// assert_eq!(iter.collect::<Vec<_>>().await, [10, 20]);
+    /// # });
+    /// ```
+    fn map<U>(self, op: impl FnMut(Self::Item) -> U) -> impl AsyncIterator<Item = U>
+    where
+        Self: Sized,
+    {
+        Map { iter: self, op }
+    }
+
+    /// Applies the async `op` to each item, waiting for each result in turn.
+    ///
+    /// ```rust
+    /// use moro::AsyncIterator;
+    ///
+    /// # futures::executor::block_on(async {
+    /// let mut iter = moro::source::range(1..3).then(async |n| n * 10);
+    /// assert_eq!(iter.collect::<Vec<_>>().await, [10, 20]);
+    /// # });
+    /// ```
+    fn then<U>(self, op: impl async FnMut(Self::Item) -> U) -> impl AsyncIterator<Item = U>
+    where
+        Self: Sized,
+    {
+        Then { iter: self, op }
+    }
+
+    /// Applies `op` to each item, yielding the results that are `Some`.
+    ///
+    /// ```rust
+    /// use moro::AsyncIterator;
+    ///
+    /// # futures::executor::block_on(async {
+    /// let mut iter = moro::source::iter(["1", "two", "3"]).filter_map(async |s| s.parse::<u32>().ok());
+    /// assert_eq!(iter.collect::<Vec<_>>().await, [1, 3]);
+    /// # });
+    /// ```
+    fn filter_map<U>(
+        self,
+        op: impl async FnMut(Self::Item) -> Option<U>,
+    ) -> impl AsyncIterator<Item = U>
+    where
+        Self: Sized,
+    {
+        FilterMap { iter: self, op }
+    }
+
+    /// Yields at most the first `n` items.
+    ///
+    /// ```rust
+    /// use moro::AsyncIterator;
+    ///
+    /// # futures::executor::block_on(async {
+    /// let mut iter = moro::source::range(0..10).take(2);
+    /// assert_eq!(iter.collect::<Vec<_>>().await, [0, 1]);
+    /// # });
+    /// ```
+    fn take(self, n: usize) -> impl AsyncIterator<Item = Self::Item>
+    where
+        Self: Sized,
+    {
+        Take {
+            iter: self,
+            remaining: n,
+        }
+    }
+
+    /// Skips the first `n` items.
+    ///
+    /// ```rust
+    /// use moro::AsyncIterator;
+    ///
+    /// # futures::executor::block_on(async {
+    /// let mut iter = moro::source::range(0..4).skip(2);
+    /// assert_eq!(iter.collect::<Vec<_>>().await, [2, 3]);
+    /// # });
+    /// ```
+    fn skip(self, n: usize) -> impl AsyncIterator<Item = Self::Item>
+    where
+        Self: Sized,
+    {
+        Skip { iter: self, n }
+    }
+
+    /// Yields items while `op` returns true, then stops.
+    ///
+    /// ```rust
+    /// use moro::AsyncIterator;
+    ///
+    /// # futures::executor::block_on(async {
+    /// let mut iter = moro::source::iter([1, 2, 5, 1]).take_while(async |n| *n < 3);
+    /// assert_eq!(iter.collect::<Vec<_>>().await, [1, 2]);
+    /// # });
+    /// ```
+    fn take_while(
+        self,
+        op: impl async FnMut(&Self::Item) -> bool,
+    ) -> impl AsyncIterator<Item = Self::Item>
+    where
+        Self: Sized,
+    {
+        TakeWhile {
+            iter: self,
+            op,
+            done: false,
+        }
+    }
+
+    /// Pairs each item with its index.
+    ///
+    /// ```rust
+    /// use moro::AsyncIterator;
+    ///
+    /// # futures::executor::block_on(async {
+    /// let mut iter = moro::source::iter(["a", "b"]).enumerate();
+    /// assert_eq!(iter.collect::<Vec<_>>().await, [(0, "a"), (1, "b")]);
+    /// # });
+    /// ```
+    fn enumerate(self) -> impl AsyncIterator<Item = (usize, Self::Item)>
+    where
+        Self: Sized,
+    {
+        Enumerate {
+            iter: self,
+            index: 0,
+        }
+    }
+
+    /// Pairs up the items of `self` and `other`, stopping when either runs out.
+    ///
+    /// ```rust
+    /// use moro::AsyncIterator;
+    ///
+    /// # futures::executor::block_on(async {
+    /// let mut iter = moro::source::range(0..10).zip(moro::source::iter(["a", "b"]));
+    /// assert_eq!(iter.collect::<Vec<_>>().await, [(0, "a"), (1, "b")]);
+    /// # });
+    /// ```
+    fn zip<J>(self, other: J) -> impl AsyncIterator<Item = (Self::Item, J::Item)>
+    where
+        Self: Sized,
+        J: AsyncIterator,
+    {
+        Zip { iter: self, other }
+    }
+
+    /// Yields the items of `self`, then those of `other`.
+    ///
+    /// ```rust
+    /// use moro::AsyncIterator;
+    ///
+    /// # futures::executor::block_on(async {
+    /// let mut iter = moro::source::range(0..2).chain(moro::source::range(5..7));
+    /// assert_eq!(iter.collect::<Vec<_>>().await, [0, 1, 5, 6]);
+    /// # });
+    /// ```
+    fn chain<J>(self, other: J) -> impl AsyncIterator<Item = Self::Item>
+    where
+        Self: Sized,
+        J: AsyncIterator<Item = Self::Item>,
+    {
+        Chain {
+            first: Some(self),
+            second: other,
+        }
+    }
+
+    /// Maps each item to an iterator with the async `op`, yielding the items of each in
+    /// turn.
+    ///
+    /// ```rust
+    /// use moro::AsyncIterator;
+    ///
+    /// # futures::executor::block_on(async {
+    /// let mut iter = moro::source::range(1..4).flat_map(async |n| moro::source::range(0..n));
+    /// assert_eq!(iter.collect::<Vec<_>>().await, [0, 0, 1, 0, 1, 2]);
+    /// # });
+    /// ```
+    fn flat_map<J>(
+        self,
+        op: impl async FnMut(Self::Item) -> J,
+    ) -> impl AsyncIterator<Item = J::Item>
+    where
+        Self: Sized,
+        J: AsyncIterator,
+    {
+        FlatMap {
+            iter: self,
+            op,
+            current: None,
+        }
+    }
+
+    /// Groups the items into `Vec`s of `size` items; the last may be shorter.
+    ///
+    /// # Panics
+    ///
+    /// If `size` is zero.
+    ///
+    /// ```rust
+    /// use moro::AsyncIterator;
+    ///
+    /// # futures::executor::block_on(async {
+    /// let mut iter = moro::source::range(0..5).chunks(2);
+    /// assert_eq!(iter.collect::<Vec<_>>().await, [vec![0, 1], vec![2, 3], vec![4]]);
+    /// # });
+    /// ```
+    fn chunks(self, size: usize) -> impl AsyncIterator<Item = Vec<Self::Item>>
+    where
+        Self: Sized,
+    {
+        assert!(size > 0, "chunk size must be at least 1");
+        Chunks { iter: self, size }
+    }
+
+    /// Allows looking at the next item without consuming it; see [`Peekable::peek`].
+    ///
+    /// ```rust
+    /// use moro::AsyncIterator;
+    ///
+    /// # futures::executor::block_on(async {
+    /// let mut iter = moro::source::range(0..2).peekable();
+    /// assert_eq!(iter.peek().await, Some(&0));
+    /// assert_eq!(iter.next().await, Some(0));
+    /// assert_eq!(iter.next().await, Some(1));
+    /// assert_eq!(iter.peek().await, None);
+    /// # });
+    /// ```
+    fn peekable(self) -> Peekable<Self>
+    where
+        Self: Sized,
+    {
+        Peekable {
+            iter: self,
+            peeked: None,
+        }
+    }
+
+    /// Stops for good after the first `None`, even if `self` would yield more items.
+    ///
+    /// ```rust
+    /// use moro::AsyncIterator;
+    ///
+    /// # futures::executor::block_on(async {
+    /// let mut iter = moro::source::range(0..1).fuse();
+    /// assert_eq!(iter.next().await, Some(0));
+    /// assert_eq!(iter.next().await, None);
+    /// assert_eq!(iter.next().await, None);
+    /// # });
+    /// ```
+    fn fuse(self) -> impl AsyncIterator<Item = Self::Item>
+    where
+        Self: Sized,
+    {
+        Fuse {
+            iter: self,
+            done: false,
+        }
+    }
+
+    /// Collects the remaining items into a collection.
+    ///
+    /// ```rust
+    /// use moro::AsyncIterator;
+    ///
+    /// # futures::executor::block_on(async {
+    /// let set: std::collections::BTreeSet<_> = moro::source::iter([2, 1, 2]).collect().await;
+    /// assert_eq!(set.into_iter().collect::<Vec<_>>(), [1, 2]);
+    /// # });
+    /// ```
+    async fn collect<C>(&mut self) -> C
+    where
+        C: Default + Extend<Self::Item>,
+    {
+        let mut collection = C::default();
+        while let Some(item) = self.next().await {
+            collection.extend(Some(item));
+        }
+        collection
+    }
+
+    /// Combines the remaining items into a single value with `op`.
+    ///
+    /// ```rust
+    /// use moro::AsyncIterator;
+    ///
+    /// # futures::executor::block_on(async {
+    /// let sum = moro::source::range(1..5).fold(0, async |sum, n| sum + n).await;
+    /// assert_eq!(sum, 10);
+    /// # });
+    /// ```
+    async fn fold<B>(&mut self, init: B, mut op: impl async FnMut(B, Self::Item) -> B) -> B {
+        let mut acc = init;
+        while let Some(item) = self.next().await {
+            acc = op(acc, item).await;
+        }
+        acc
+    }
+
+    /// Converts this iterator into a [`futures::Stream`]. See also
+    /// [`source::from_stream`](crate::source::from_stream).
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use futures::StreamExt;
+    /// use moro::AsyncIterator;
+    ///
+    /// # futures::executor::block_on(async {
+    /// let stream = moro::source::range(0..3).into_futures_stream();
+    /// assert_eq!(stream.collect::<Vec<_>>().await, [0, 1, 2]);
+    /// # });
+    /// ```
+    fn into_futures_stream(self) -> impl futures::Stream<Item = Self::Item>
+    where
+        Self: Sized,
+    {
+        futures::stream::unfold(self, async |mut iter| {
+            let item = iter.next().await?;
+            Some((item, iter))
+        })
+    }
 }
 
 pub trait IntoAsyncIter {
     type Item;
 
-    // FIXME: The Scope type needs to not carry R.
-    fn into_async_iter<R: Send>(
-        self,
-        scope: &Scope<'_, '_, R>,
-    ) -> impl AsyncIterator<Item = Self::Item>;
+    fn into_async_iter(self, scope: &Scope<'_, '_>) -> impl AsyncIterator<Item = Self::Item>;
 }
 
 impl<T: AsyncIterator> IntoAsyncIter for T {
     type Item = T::Item;
 
-    fn into_async_iter<R: Send>(
-        self,
-        _scope: &Scope<'_, '_, R>,
-    ) -> impl AsyncIterator<Item = Self::Item> {
+    fn into_async_iter(self, _scope: &Scope<'_, '_>) -> impl AsyncIterator<Item = Self::Item> {
         self
     }
 }
@@ -75,3 +400,502 @@ where
         }
     }
 }
+
+struct Map<I, O> {
+    iter: I,
+    op: O,
+}
+
+impl<I, O, U> AsyncIterator for Map<I, O>
+where
+    I: AsyncIterator,
+    O: FnMut(I::Item) -> U,
+{
+    type Item = U;
+
+    async fn next(&mut self) -> Option<Self::Item> {
+        self.iter.next().await.map(&mut self.op)
+    }
+}
+
+struct Then<I, O> {
+    iter: I,
+    op: O,
+}
+
+impl<I, O, U> AsyncIterator for Then<I, O>
+where
+    I: AsyncIterator,
+    O: async FnMut(I::Item) -> U,
+{
+    type Item = U;
+
+    async fn next(&mut self) -> Option<Self::Item> {
+        let item = self.iter.next().await?;
+        Some((self.op)(item).await)
+    }
+}
+
+struct FilterMap<I, O> {
+    iter: I,
+    op: O,
+}
+
+impl<I, O, U> AsyncIterator for FilterMap<I, O>
+where
+    I: AsyncIterator,
+    O: async FnMut(I::Item) -> Option<U>,
+{
+    type Item = U;
+
+    async fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let item = self.iter.next().await?;
+            if let Some(mapped) = (self.op)(item).await {
+                return Some(mapped);
+            }
+        }
+    }
+}
+
+struct Take<I> {
+    iter: I,
+    remaining: usize,
+}
+
+impl<I: AsyncIterator> AsyncIterator for Take<I> {
+    type Item = I::Item;
+
+    async fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+        self.iter.next().await
+    }
+}
+
+struct Skip<I> {
+    iter: I,
+
+    /// The number of items still to skip.
+    n: usize,
+}
+
+impl<I: AsyncIterator> AsyncIterator for Skip<I> {
+    type Item = I::Item;
+
+    async fn next(&mut self) -> Option<Self::Item> {
+        while self.n > 0 {
+            self.iter.next().await?;
+            self.n -= 1;
+        }
+        self.iter.next().await
+    }
+}
+
+struct TakeWhile<I, O> {
+    iter: I,
+    op: O,
+    done: bool,
+}
+
+impl<I, O> AsyncIterator for TakeWhile<I, O>
+where
+    I: AsyncIterator,
+    O: async FnMut(&I::Item) -> bool,
+{
+    type Item = I::Item;
+
+    async fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let item = self.iter.next().await?;
+        if (self.op)(&item).await {
+            Some(item)
+        } else {
+            self.done = true;
+            None
+        }
+    }
+}
+
+struct Enumerate<I> {
+    iter: I,
+    index: usize,
+}
+
+impl<I: AsyncIterator> AsyncIterator for Enumerate<I> {
+    type Item = (usize, I::Item);
+
+    async fn next(&mut self) -> Option<Self::Item> {
+        let item = self.iter.next().await?;
+        let index = self.index;
+        self.index += 1;
+        Some((index, item))
+    }
+}
+
+struct Zip<I, J> {
+    iter: I,
+    other: J,
+}
+
+impl<I, J> AsyncIterator for Zip<I, J>
+where
+    I: AsyncIterator,
+    J: AsyncIterator,
+{
+    type Item = (I::Item, J::Item);
+
+    async fn next(&mut self) -> Option<Self::Item> {
+        let item = self.iter.next().await?;
+        let other = self.other.next().await?;
+        Some((item, other))
+    }
+}
+
+struct Chain<I, J> {
+    /// `None` once the first iterator is exhausted.
+    first: Option<I>,
+    second: J,
+}
+
+impl<I, J> AsyncIterator for Chain<I, J>
+where
+    I: AsyncIterator,
+    J: AsyncIterator<Item = I::Item>,
+{
+    type Item = I::Item;
+
+    async fn next(&mut self) -> Option<Self::Item> {
+        if let Some(first) = &mut self.first {
+            if let Some(item) = first.next().await {
+                return Some(item);
+            }
+            self.first = None;
+        }
+        self.second.next().await
+    }
+}
+
+struct FlatMap<I, O, J> {
+    iter: I,
+    op: O,
+
+    /// The iterator for the most recent item, until it is exhausted.
+    current: Option<J>,
+}
+
+impl<I, O, J> AsyncIterator for FlatMap<I, O, J>
+where
+    I: AsyncIterator,
+    O: async FnMut(I::Item) -> J,
+    J: AsyncIterator,
+{
+    type Item = J::Item;
+
+    async fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(current) = &mut self.current {
+                if let Some(item) = current.next().await {
+                    return Some(item);
+                }
+                self.current = None;
+            }
+            let item = self.iter.next().await?;
+            self.current = Some((self.op)(item).await);
+        }
+    }
+}
+
+struct Chunks<I> {
+    iter: I,
+    size: usize,
+}
+
+impl<I: AsyncIterator> AsyncIterator for Chunks<I> {
+    type Item = Vec<I::Item>;
+
+    async fn next(&mut self) -> Option<Self::Item> {
+        let mut chunk = Vec::with_capacity(self.size);
+        while chunk.len() < self.size {
+            match self.iter.next().await {
+                Some(item) => chunk.push(item),
+                None => break,
+            }
+        }
+        if chunk.is_empty() {
+            None
+        } else {
+            Some(chunk)
+        }
+    }
+}
+
+/// An iterator that can look at its next item without consuming it. Created by
+/// [`AsyncIterator::peekable`].
+pub struct Peekable<I: AsyncIterator> {
+    iter: I,
+
+    /// The next item, if it has been peeked at.
+    peeked: Option<Option<I::Item>>,
+}
+
+impl<I: AsyncIterator> Peekable<I> {
+    /// Returns a reference to the next item, without consuming it.
+    pub async fn peek(&mut self) -> Option<&I::Item> {
+        if self.peeked.is_none() {
+            self.peeked = Some(self.iter.next().await);
+        }
+        self.peeked.as_ref().and_then(Option::as_ref)
+    }
+}
+
+impl<I: AsyncIterator> AsyncIterator for Peekable<I> {
+    type Item = I::Item;
+
+    async fn next(&mut self) -> Option<Self::Item> {
+        match self.peeked.take() {
+            Some(item) => item,
+            None => self.iter.next().await,
+        }
+    }
+}
+
+struct Fuse<I> {
+    iter: I,
+    done: bool,
+}
+
+impl<I: AsyncIterator> AsyncIterator for Fuse<I> {
+    type Item = I::Item;
+
+    async fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let item = self.iter.next().await;
+        self.done = item.is_none();
+        item
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::Cell, num::ParseIntError};
+
+    use futures::executor::block_on;
+
+    use super::AsyncIterator;
+    use crate::source::{iter, range};
+
+    /// Yields `0..len`, counting the items pulled from it.
+    struct Counted<'a> {
+        pulled: &'a Cell<u32>,
+        len: u32,
+    }
+
+    impl AsyncIterator for Counted<'_> {
+        type Item = u32;
+
+        async fn next(&mut self) -> Option<u32> {
+            let n = self.pulled.get();
+            if n == self.len {
+                return None;
+            }
+            self.pulled.set(n + 1);
+            Some(n)
+        }
+    }
+
+    /// Yields `None` and `Some(n)` in turn, as a source may after it has run out.
+    struct Flaky(u32);
+
+    impl AsyncIterator for Flaky {
+        type Item = u32;
+
+        async fn next(&mut self) -> Option<u32> {
+            self.0 += 1;
+            self.0.is_multiple_of(2).then_some(self.0)
+        }
+    }
+
+    /// An empty source.
+    fn empty() -> impl AsyncIterator<Item = u32> {
+        range(0..0)
+    }
+
+    #[test]
+    fn empty_input() {
+        block_on(async {
+            assert_eq!(empty().map(|n| n + 1).next().await, None);
+            assert_eq!(empty().filter(async |_| true).next().await, None);
+            assert_eq!(empty().filter_map(async |n| Some(n)).next().await, None);
+            assert_eq!(empty().then(async |n| n).next().await, None);
+            assert_eq!(empty().take(3).next().await, None);
+            assert_eq!(empty().skip(3).next().await, None);
+            assert_eq!(empty().take_while(async |_| true).next().await, None);
+            assert_eq!(empty().enumerate().next().await, None);
+            assert_eq!(empty().zip(range(0..3)).next().await, None);
+            assert_eq!(range(0..3).zip(empty()).next().await, None);
+            assert_eq!(empty().chain(empty()).next().await, None);
+            assert_eq!(empty().flat_map(async |n| range(0..n)).next().await, None);
+            assert_eq!(range(0..3).flat_map(async |_| empty()).next().await, None);
+            assert_eq!(empty().chunks(2).next().await, None);
+            assert_eq!(empty().peekable().peek().await, None);
+            assert_eq!(empty().fuse().next().await, None);
+            assert_eq!(empty().collect::<Vec<_>>().await, []);
+            assert_eq!(empty().fold(7, async |acc, n| acc + n).await, 7);
+        });
+    }
+
+    #[test]
+    fn take_zero() {
+        block_on(async {
+            let pulled = Cell::new(0);
+            let mut iter = Counted {
+                pulled: &pulled,
+                len: 10,
+            }
+            .take(0);
+            assert_eq!(iter.next().await, None);
+            assert_eq!(pulled.get(), 0);
+        });
+    }
+
+    #[test]
+    fn skip_past_the_end() {
+        block_on(async {
+            let mut iter = range(0..4).skip(10);
+            assert_eq!(iter.next().await, None);
+            assert_eq!(iter.next().await, None);
+        });
+    }
+
+    #[test]
+    fn zip_stops_at_the_shorter() {
+        block_on(async {
+            let zipped: Vec<_> = range(0..10).zip(iter(["a", "b"])).collect().await;
+            assert_eq!(zipped, [(0, "a"), (1, "b")]);
+
+            let zipped: Vec<_> = iter(["a", "b"]).zip(range(0..10)).collect().await;
+            assert_eq!(zipped, [("a", 0), ("b", 1)]);
+        });
+    }
+
+    #[test]
+    fn chunks_of_an_exact_multiple() {
+        block_on(async {
+            let chunks: Vec<_> = range(0..4).chunks(2).collect().await;
+            assert_eq!(chunks, [vec![0, 1], vec![2, 3]]);
+        });
+    }
+
+    #[test]
+    fn next_after_peeking_at_the_end() {
+        block_on(async {
+            let mut iter = range(0..1).peekable();
+            assert_eq!(iter.next().await, Some(0));
+            assert_eq!(iter.peek().await, None);
+            assert_eq!(iter.next().await, None);
+        });
+    }
+
+    #[test]
+    fn fuse_stops_for_good() {
+        block_on(async {
+            let mut iter = Flaky(0);
+            assert_eq!(iter.next().await, None);
+            assert_eq!(iter.next().await, Some(2));
+
+            let mut iter = Flaky(1).fuse();
+            assert_eq!(iter.next().await, Some(2));
+            assert_eq!(iter.next().await, None);
+            assert_eq!(iter.next().await, None);
+        });
+    }
+
+    /// Stopping early pulls no more items from the source than were needed.
+    #[test]
+    fn early_break() {
+        block_on(async {
+            let pulled = Cell::new(0);
+            let mut iter = Counted {
+                pulled: &pulled,
+                len: 100,
+            }
+            .then(async |n| n * 10)
+            .enumerate();
+            while let Some((index, _)) = iter.next().await {
+                if index == 2 {
+                    break;
+                }
+            }
+            assert_eq!(pulled.get(), 3);
+
+            // The iterator picks up where it left off.
+            assert_eq!(iter.next().await, Some((3, 30)));
+            assert_eq!(pulled.get(), 4);
+
+            let pulled = Cell::new(0);
+            let taken: Vec<_> = Counted {
+                pulled: &pulled,
+                len: 100,
+            }
+            .take_while(async |n| *n < 3)
+            .collect()
+            .await;
+            assert_eq!(taken, [0, 1, 2]);
+            assert_eq!(pulled.get(), 4);
+
+            let pulled = Cell::new(0);
+            let mut iter = Counted {
+                pulled: &pulled,
+                len: 100,
+            }
+            .flat_map(async |n| range(0..n));
+            assert_eq!(iter.next().await, Some(0));
+            assert_eq!(pulled.get(), 2);
+        });
+    }
+
+    /// Errors are items like any other: they flow through the adapters, and stop an
+    /// iteration only when asked to.
+    #[test]
+    fn errors_mid_stream() {
+        let parse = async |s: &str| s.parse::<u32>();
+        block_on(async {
+            let input = ["1", "2", "x", "4"];
+
+            let parsed: Vec<_> = iter(input).then(parse).collect().await;
+            assert_eq!(parsed.len(), 4);
+            assert!(parsed[2].is_err());
+            assert_eq!(parsed[3], Ok(4));
+
+            let valid: Vec<_> = iter(input)
+                .filter_map(async |s| parse(s).await.ok())
+                .collect()
+                .await;
+            assert_eq!(valid, [1, 2, 4]);
+
+            let until_error: Vec<_> = iter(input)
+                .then(parse)
+                .take_while(async |n| n.is_ok())
+                .collect()
+                .await;
+            assert_eq!(until_error, [Ok(1), Ok(2)]);
+
+            let sum: Result<u32, ParseIntError> = iter(input)
+                .then(parse)
+                .fold(Ok(0), async |sum, n| Ok(sum? + n?))
+                .await;
+            assert!(sum.is_err());
+
+            let chunks: Vec<_> = iter(input).then(parse).chunks(3).collect().await;
+            assert_eq!(chunks[1], [Ok(4)]);
+            assert!(chunks[0][2].is_err());
+        });
+    }
+}
@@ -1,9 +1,18 @@
-use std::{pin::Pin, sync::Arc, task::Poll};
+use std::{
+    pin::Pin,
+    rc::Rc,
+    sync::Arc,
+    task::{Context, Poll},
+};
 
-use futures::Future;
+use futures::{task::waker_ref, Future};
 use pin_project::{pin_project, pinned_drop};
 
-use crate::scope::Scope;
+use crate::{
+    job::JobWakers,
+    registry::{JobRecord, Polling, Status},
+    CancellableScope,
+};
 
 /// The future for a scope's "body".
 ///
@@ -16,30 +25,35 @@ use crate::scope::Scope;
 #[pin_project(PinnedDrop)]
 pub(crate) struct Body<'scope, 'env: 'scope, R, F>
 where
-    R: Send,
     R: 'env,
 {
     #[pin]
     body_future: Option<F>,
     result: Option<R>,
-    scope: Arc<Scope<'scope, 'env, R>>,
+    scope: Rc<CancellableScope<'scope, 'env, R>>,
+
+    /// The body is polled with these wakers, so that their clones can be counted to tell
+    /// whether the scope has stalled.
+    wakers: Arc<JobWakers>,
 }
 
-impl<'scope, 'env, R, F> Body<'scope, 'env, R, F>
-where
-    R: Send,
-{
+impl<'scope, 'env, R, F> Body<'scope, 'env, R, F> {
     /// # Unsafe contract
     ///
     /// - `future` will be dropped BEFORE `scope`
-    pub(crate) fn new(future: F, scope: Arc<Scope<'scope, 'env, R>>) -> Self {
+    pub(crate) fn new(future: F, scope: Rc<CancellableScope<'scope, 'env, R>>) -> Self {
         Self {
             body_future: Some(future),
             result: None,
             scope,
+            wakers: Default::default(),
         }
     }
 
+    pub(crate) fn scope(&self) -> &CancellableScope<'scope, 'env, R> {
+        &self.scope
+    }
+
     fn clear(self: Pin<&mut Self>) {
         let mut this = self.project();
         this.body_future.set(None);
@@ -49,10 +63,7 @@ where
 }
 
 #[pinned_drop]
-impl<'scope, 'env, R, F> PinnedDrop for Body<'scope, 'env, R, F>
-where
-    R: Send,
-{
+impl<'scope, 'env, R, F> PinnedDrop for Body<'scope, 'env, R, F> {
     fn drop(self: Pin<&mut Self>) {
         // Fulfill our unsafe contract and ensure we drop other fields
         // before we drop scope.
@@ -62,37 +73,129 @@ where
 
 impl<'scope, 'env, R, F> Future for Body<'scope, 'env, R, F>
 where
-    R: Send,
     F: Future<Output = R>,
 {
     type Output = R;
 
-    fn poll(self: std::pin::Pin<&mut Self>, cx: &mut std::task::Context<'_>) -> Poll<Self::Output> {
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match ready!(self.poll_ended(cx)) {
+            Some(v) => Poll::Ready(v),
+            None => unreachable!("scope terminated without a value, but not by a deadline"),
+        }
+    }
+}
+
+impl<'scope, 'env, R, F> Body<'scope, 'env, R, F>
+where
+    F: Future<Output = R>,
+{
+    /// Polls the scope until it has ended. The result is `None` if the scope was
+    /// terminated without a terminal value, which only its deadline does (see
+    /// [`ScopeBody::move_on_after`][crate::ScopeBody::move_on_after]).
+    pub(crate) fn poll_ended(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<R>> {
+        let poll = self.as_mut().poll_scope(cx);
+        let this = self.project();
+        if poll.is_pending() {
+            let body = this
+                .body_future
+                .is_some()
+                .then(|| this.wakers.waiting(None));
+            this.scope.check_stalled(body);
+        }
+        poll
+    }
+
+    /// Polls the body and the jobs, as described for [`Scope::poll_jobs`][crate::Scope].
+    fn poll_scope(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<R>> {
         let mut this = self.project();
 
         // If the body is not yet finished, poll that. Once it becomes finished,
-        // we will update `this.result.
-        if let Some(body_future) = this.body_future.as_mut().as_pin_mut() {
-            match body_future.poll(cx) {
-                Poll::Ready(r) => {
-                    *this.result = Some(r);
-                    this.body_future.set(None);
+        // we will update `this.result. Once the scope is terminated, the body
+        // does no more work (though shielded jobs may).
+        if !this.scope.is_terminated() {
+            if let Some(body_future) = this.body_future.as_mut().as_pin_mut() {
+                let record = this.scope.record();
+                let polling = record.map(Polling::enter);
+                let poll = this.scope.span().in_scope(|| {
+                    // Poll with the body's own waker, so that its clones can be counted.
+                    this.wakers.entry.register(cx.waker());
+                    this.wakers.start_poll();
+                    let waker = waker_ref(this.wakers);
+                    body_future.poll(&mut Context::from_waker(&waker))
+                });
+                drop(polling);
+                match poll {
+                    Poll::Ready(r) => {
+                        *this.result = Some(r);
+                        this.body_future.set(None);
+                        set_status(record, Status::Done);
+                    }
+                    Poll::Pending => {
+                        // The body recorded an error and was stopped; see
+                        // `ErrorMode::CollectAll`.
+                        if this.scope.take_stop_current() {
+                            this.body_future.set(None);
+                            set_status(record, Status::Failed);
+                        } else {
+                            set_status(record, Status::Pending);
+                        }
+                    }
                 }
-                Poll::Pending => {}
+            }
+        }
+
+        loop {
+            let jobs = this.scope.poll_jobs(cx);
+
+            // If a job panicked, cancel the remaining jobs (most recently spawned first),
+            // then the body, and only then resume the panic.
+            if let Some(payload) = this.scope.take_panic() {
+                this.scope.clear();
+                this.body_future.set(None);
+                this.result.take();
+                std::panic::resume_unwind(payload);
+            }
+
+            ready!(jobs);
+
+            // The scope has ended once it is terminated or its body is done. Run its
+            // finalizers, if any, before returning.
+            let ended = this.scope.is_terminated() || this.body_future.is_none();
+            if !(ended && this.scope.start_deferred()) {
+                break;
             }
         }
 
         // Check if the scope is ready.
         //
-        // If polling the scope returns `Some`, then the scope was early terminated,
-        // so forward that result. Otherwise, the `result` from our body future
-        // should be available, so return that.
-        match ready!(this.scope.poll_jobs(cx)) {
-            Some(v) => return Poll::Ready(v),
-            None => match this.result.take() {
-                None => Poll::Pending,
-                Some(v) => Poll::Ready(v),
-            },
+        // If the scope was early terminated, forward the value it was terminated
+        // with, or `None` if its deadline terminated it. Otherwise, the `result` from
+        // our body future should be available, so return that. If the body was
+        // stopped instead, return the last error it collected (the rest are picked up
+        // by `ScopeBody::with_error_mode`).
+        if let Some(v) = this.scope.take_terminated() {
+            return Poll::Ready(Some(v));
+        }
+        if this.scope.is_terminated() {
+            return Poll::Ready(None);
+        }
+        if this.body_future.is_some() {
+            return Poll::Pending;
         }
+        match this
+            .result
+            .take()
+            .or_else(|| this.scope.take_last_collected())
+        {
+            None => Poll::Pending,
+            Some(v) => Poll::Ready(Some(v)),
+        }
+    }
+}
+
+/// Updates the status of the scope body in the task tree, if the scope records one.
+fn set_status(record: Option<&JobRecord>, status: Status) {
+    if let Some(record) = record {
+        record.status.set(status);
     }
 }
@@ -0,0 +1,172 @@
+use std::{
+    cell::{Cell, RefCell},
+    rc::{Rc, Weak},
+};
+
+use futures::Future;
+
+use crate::{job::ErasedJob, Scope, Spawned};
+
+/// A region within a [`Scope`] whose jobs can be cancelled as a group, without
+/// terminating the enclosing scope. Created with [`Scope::cancel_scope`].
+///
+/// Cancel scopes can be nested with [`CancelScope::cancel_scope`]; cancelling a region
+/// also cancels every region nested inside it.
+///
+/// Only jobs spawned through the cancel scope belong to it. A job spawned with
+/// [`Scope::spawn`] from inside one of those jobs belongs to the enclosing scope.
+///
+/// # Examples
+///
+/// ```rust
+/// # futures::executor::block_on(async {
+/// let result = moro::async_scope!(|scope| {
+///     let region = scope.cancel_scope();
+///     let job = region.spawn(futures::future::pending::<()>());
+///     region.cancel();
+///     assert!(job.await.is_err());
+///
+///     // the enclosing scope keeps running
+///     scope.spawn(async { 22 }).output().await
+/// }).await;
+/// assert_eq!(result, 22);
+/// # });
+/// ```
+pub struct CancelScope<'scope, 'env: 'scope> {
+    scope: &'scope Scope<'scope, 'env>,
+    state: Rc<CancelState<'scope>>,
+}
+
+#[derive(Default)]
+struct CancelState<'scope> {
+    cancelled: Cell<bool>,
+
+    /// The jobs spawned in this region.
+    jobs: RefCell<Vec<Rc<dyn ErasedJob + 'scope>>>,
+
+    /// Regions nested within this one, for as long as a handle to them is alive.
+    children: RefCell<Vec<Weak<CancelState<'scope>>>>,
+
+    /// The region this one is nested in, which inherits its jobs once it is dropped.
+    parent: Option<Rc<CancelState<'scope>>>,
+}
+
+impl CancelState<'_> {
+    fn cancel(&self) {
+        self.cancelled.set(true);
+        for job in self.jobs.take() {
+            job.abort();
+        }
+        for child in self.children.take() {
+            if let Some(child) = child.upgrade() {
+                child.cancel();
+            }
+        }
+    }
+}
+
+impl Drop for CancelState<'_> {
+    fn drop(&mut self) {
+        // Nobody can cancel this region directly anymore, but its jobs are still
+        // cancelled along with the enclosing region.
+        let Some(parent) = &self.parent else {
+            return;
+        };
+        let jobs = self
+            .jobs
+            .get_mut()
+            .drain(..)
+            .filter(|job| !job.is_finished());
+        if parent.cancelled.get() {
+            for job in jobs {
+                job.abort();
+            }
+        } else {
+            parent.jobs.borrow_mut().extend(jobs);
+        }
+    }
+}
+
+impl<'scope, 'env> CancelScope<'scope, 'env> {
+    pub(crate) fn new(scope: &'scope Scope<'scope, 'env>) -> Self {
+        Self {
+            scope,
+            state: Default::default(),
+        }
+    }
+
+    /// Spawn a job within this region. It behaves like a job spawned with [`Scope::spawn`],
+    /// but is aborted when the region is cancelled. If the region has already been
+    /// cancelled, the job is aborted before it starts.
+    #[track_caller]
+    pub fn spawn<T>(&self, future: impl Future<Output = T> + 'scope) -> Spawned<'scope, T>
+    where
+        T: 'scope,
+    {
+        let spawned = self.scope.spawn(future);
+        if self.is_cancelled() {
+            spawned.abort();
+        } else {
+            // Finished jobs have nothing left to cancel; dropping them frees their output
+            // if it was never taken. Pruning only when the list is full keeps spawning
+            // amortized O(1). The outputs are dropped after the list is released, since
+            // they may hold handles to nested regions.
+            let mut jobs = self.state.jobs.borrow_mut();
+            let finished: Vec<_> = if jobs.len() == jobs.capacity() {
+                jobs.extract_if(.., |job| job.is_finished()).collect()
+            } else {
+                Vec::new()
+            };
+            jobs.push(spawned.job());
+            drop(jobs);
+            drop(finished);
+        }
+        spawned
+    }
+
+    /// Create a region nested within this one. It is cancelled when this region is.
+    pub fn cancel_scope(&self) -> CancelScope<'scope, 'env> {
+        let child = CancelScope {
+            scope: self.scope,
+            state: Rc::new(CancelState {
+                cancelled: Cell::new(false),
+                jobs: Default::default(),
+                children: Default::default(),
+                parent: Some(self.state.clone()),
+            }),
+        };
+        if self.is_cancelled() {
+            child.cancel();
+        } else {
+            // As with jobs, regions whose handles are all gone are pruned when the list is
+            // full.
+            let mut children = self.state.children.borrow_mut();
+            if children.len() == children.capacity() {
+                children.retain(|child| child.strong_count() > 0);
+            }
+            children.push(Rc::downgrade(&child.state));
+        }
+        child
+    }
+
+    /// Cancel every job spawned within this region and within any nested region.
+    /// Jobs spawned in the region afterwards are aborted immediately.
+    pub fn cancel(&self) {
+        self.state.cancel();
+    }
+
+    /// True if [`cancel`](Self::cancel) has been invoked on this region or an
+    /// enclosing one.
+    pub fn is_cancelled(&self) -> bool {
+        self.state.cancelled.get()
+    }
+}
+
+impl Clone for CancelScope<'_, '_> {
+    fn clone(&self) -> Self {
+        Self {
+            scope: self.scope,
+            state: self.state.clone(),
+        }
+    }
+}
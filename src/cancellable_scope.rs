@@ -0,0 +1,178 @@
+use std::{
+    cell::{Cell, RefCell},
+    ops::Deref,
+};
+
+use futures::Future;
+
+use crate::Scope;
+
+/// The scope handed to the body of an [`async_scope`][crate::async_scope]. It derefs to
+/// [`Scope`], so everything that can be done with a `Scope` can be done with it, and it
+/// additionally knows the type `R` of the scope's result, so it can
+/// [`terminate`](Self::terminate) the scope early with a value of that type.
+pub struct CancellableScope<'scope, 'env: 'scope, R: 'env> {
+    scope: Scope<'scope, 'env>,
+
+    /// The value the scope was terminated with, if any.
+    terminated: RefCell<Option<R>>,
+
+    /// In [collect-all](crate::ErrorMode::CollectAll) mode, picks out the values passed
+    /// to `terminate` that are errors to collect rather than the scope's final value.
+    collect: Cell<Option<Collect<R>>>,
+
+    /// The errors collected so far, in the order they were reported.
+    collected: RefCell<Vec<R>>,
+}
+
+/// Picks out the values passed to `terminate` that are errors to collect.
+type Collect<R> = fn(&R) -> bool;
+
+impl<'scope, 'env, R> CancellableScope<'scope, 'env, R> {
+    #[track_caller]
+    pub(crate) fn new() -> Self {
+        Self {
+            scope: Scope::new(),
+            terminated: Default::default(),
+            collect: Default::default(),
+            collected: Default::default(),
+        }
+    }
+
+    /// Takes the value the scope was terminated with, if any.
+    pub(crate) fn take_terminated(&self) -> Option<R> {
+        self.terminated.take()
+    }
+
+    /// Switches to collect-all mode, collecting the values for which `collect` is true.
+    pub(crate) fn set_collect(&self, collect: Collect<R>) {
+        self.collect.set(Some(collect));
+    }
+
+    /// Takes the errors collected so far.
+    pub(crate) fn take_collected(&self) -> Vec<R> {
+        self.collected.take()
+    }
+
+    /// Takes the most recently collected error, if any.
+    pub(crate) fn take_last_collected(&self) -> Option<R> {
+        self.collected.borrow_mut().pop()
+    }
+
+    /// Terminate the scope immediately -- all existing jobs will stop at their next await point
+    /// and never wake up again. Anything on their stacks will be dropped. This is most useful
+    /// for propagating errors, but it can be used to propagate any kind of final value (e.g.,
+    /// perhaps you are searching for something and want to stop once you find it.)
+    ///
+    /// This returns a future that you should await, but it will never complete
+    /// (because you will never be reawoken). Since termination takes effect at the next
+    /// await point, awaiting the returned future ensures that your current future stops
+    /// immediately.
+    ///
+    /// In [collect-all](crate::ErrorMode::CollectAll) mode, an error is recorded instead
+    /// and only the current job (or the scope body) stops; the rest of the scope keeps
+    /// running.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # futures::executor::block_on(async {
+    /// let result = moro::async_scope!(|scope| {
+    ///     scope.spawn(async { /* ... */ });
+    ///
+    ///     // Calling `scope.terminate` here will terminate the async
+    ///     // scope and use the string `"cancellation-value"` as
+    ///     // the final value.
+    ///     let result: () = scope.terminate("cancellation-value").await;
+    ///     unreachable!() // this code never executes
+    /// }).await;
+    ///
+    /// assert_eq!(result, "cancellation-value");
+    /// # });
+    /// ```
+    ///
+    /// Jobs can terminate the scope as well:
+    ///
+    /// ```rust
+    /// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+    /// let result = moro::async_scope!(|scope| {
+    ///     let job = scope.spawn(futures::future::pending::<()>());
+    ///     scope.spawn(async {
+    ///         tokio::task::yield_now().await;
+    ///         let () = scope.terminate("stopped by a job").await;
+    ///     });
+    ///     job.await;
+    ///     unreachable!()
+    /// }).await;
+    ///
+    /// assert_eq!(result, "stopped by a job");
+    /// # });
+    /// ```
+    pub fn terminate<T>(&'scope self, value: R) -> impl Future<Output = T> + 'scope
+    where
+        T: 'scope,
+    {
+        if self.collect.get().is_some_and(|collect| collect(&value)) {
+            self.collected.borrow_mut().push(value);
+            self.scope.stop_current();
+        } else {
+            let mut terminated = self.terminated.borrow_mut();
+            if terminated.is_none() {
+                *terminated = Some(value);
+            }
+            std::mem::drop(terminated);
+            self.scope.set_terminated();
+        }
+
+        // Either way, the caller is never polled again.
+        futures::future::pending()
+    }
+
+    /// Like [`terminate`](Self::terminate), but converts `value` into the scope's result
+    /// type with `From`. `terminate` takes the result type itself, so that it can drive
+    /// type inference when nothing else names it.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # futures::executor::block_on(async {
+    /// #[derive(Debug)]
+    /// enum Stopped {
+    ///     EndOfInput,
+    ///     BadNumber(std::num::ParseIntError),
+    /// }
+    ///
+    /// impl From<std::num::ParseIntError> for Stopped {
+    ///     fn from(e: std::num::ParseIntError) -> Self {
+    ///         Stopped::BadNumber(e)
+    ///     }
+    /// }
+    ///
+    /// // The scope's result says why it stopped.
+    /// let stopped = moro::async_scope!(|scope| -> Stopped {
+    ///     for input in ["1", "2", "x", "4"] {
+    ///         if let Err(e) = input.parse::<u32>() {
+    ///             let () = scope.terminate_into(e).await;
+    ///         }
+    ///     }
+    ///     Stopped::EndOfInput
+    /// })
+    /// .await;
+    /// assert!(matches!(stopped, Stopped::BadNumber(_)));
+    /// # });
+    /// ```
+    pub fn terminate_into<T>(&'scope self, value: impl Into<R>) -> impl Future<Output = T> + 'scope
+    where
+        T: 'scope,
+    {
+        self.terminate(value.into())
+    }
+}
+
+impl<'scope, 'env, R> Deref for CancellableScope<'scope, 'env, R> {
+    type Target = Scope<'scope, 'env>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.scope
+    }
+}
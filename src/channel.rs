@@ -0,0 +1,422 @@
+use std::{
+    cell::{Cell, RefCell},
+    collections::VecDeque,
+    fmt,
+    future::Future,
+    marker::PhantomData,
+    pin::Pin,
+    rc::{Rc, Weak},
+    task::{Context, Poll, Waker},
+};
+
+/// What a scope needs from its channels, which are of different types.
+pub(crate) trait Channel {
+    /// Closes the channel, when the scope is terminated.
+    fn close(&self);
+
+    /// The data pointers of the wakers held by the channel: that of the receiver
+    /// waiting for a message, if any, and those of the senders waiting for room in the
+    /// buffer. Used to tell whether the scope has stalled.
+    fn waiting(&self) -> ChannelWaiters;
+
+    /// Fails the receive that is waiting with [`RecvError::Stalled`], once the scope has
+    /// stalled.
+    fn fail_receiver(&self);
+}
+
+/// See [`Channel::waiting`].
+#[derive(Default)]
+pub(crate) struct ChannelWaiters {
+    pub(crate) receiver: Option<*const ()>,
+    pub(crate) senders: Vec<*const ()>,
+}
+
+/// The state shared by the endpoints of a channel.
+struct Chan<T> {
+    buffer: RefCell<VecDeque<T>>,
+    capacity: usize,
+
+    /// Set when the receiver is dropped or the scope is terminated.
+    closed: Cell<bool>,
+
+    /// The number of senders that have not been dropped.
+    senders: Cell<usize>,
+
+    receiver: RefCell<Option<Waker>>,
+
+    /// Set when the scope stalls while the receiver waits, and cleared once the receiver
+    /// has seen it, or when a message is sent.
+    stalled: Cell<bool>,
+
+    /// Senders waiting for room in the buffer.
+    blocked: RefCell<Vec<Waker>>,
+}
+
+impl<T> Chan<T> {
+    fn new(capacity: usize) -> Self {
+        Self {
+            buffer: RefCell::new(VecDeque::with_capacity(capacity)),
+            capacity,
+            closed: Cell::new(false),
+            senders: Cell::new(1),
+            receiver: Default::default(),
+            stalled: Cell::new(false),
+            blocked: Default::default(),
+        }
+    }
+
+    fn add_sender(&self) {
+        self.senders.set(self.senders.get() + 1);
+    }
+
+    fn drop_sender(&self) {
+        self.senders.set(self.senders.get() - 1);
+        if self.senders.get() == 0 {
+            self.wake_receiver();
+        }
+    }
+
+    fn try_send(&self, value: T) -> Result<(), SendError<T>> {
+        if self.closed.get() {
+            return Err(SendError(value));
+        }
+        self.buffer.borrow_mut().push_back(value);
+        self.stalled.set(false);
+        self.wake_receiver();
+        Ok(())
+    }
+
+    /// Waits for room in the buffer, or for the channel to close.
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<()> {
+        if !self.closed.get() && self.buffer.borrow().len() >= self.capacity {
+            let mut blocked = self.blocked.borrow_mut();
+            if !blocked.iter().any(|waker| waker.will_wake(cx.waker())) {
+                blocked.push(cx.waker().clone());
+            }
+            return Poll::Pending;
+        }
+        Poll::Ready(())
+    }
+
+    fn poll_recv(&self, cx: &mut Context<'_>) -> Poll<Result<T, RecvError>> {
+        if let Some(value) = self.buffer.borrow_mut().pop_front() {
+            for waker in self.blocked.take() {
+                waker.wake();
+            }
+            return Poll::Ready(Ok(value));
+        }
+
+        if self.closed.get() || self.senders.get() == 0 {
+            return Poll::Ready(Err(RecvError::Closed));
+        }
+        if self.stalled.take() {
+            return Poll::Ready(Err(RecvError::Stalled));
+        }
+
+        *self.receiver.borrow_mut() = Some(cx.waker().clone());
+        Poll::Pending
+    }
+
+    fn wake_receiver(&self) {
+        if let Some(waker) = self.receiver.take() {
+            waker.wake();
+        }
+    }
+}
+
+impl<T> Channel for Chan<T> {
+    fn close(&self) {
+        self.closed.set(true);
+        self.wake_receiver();
+        for waker in self.blocked.take() {
+            waker.wake();
+        }
+    }
+
+    fn waiting(&self) -> ChannelWaiters {
+        ChannelWaiters {
+            receiver: self.receiver.borrow().as_ref().map(Waker::data),
+            senders: self.blocked.borrow().iter().map(Waker::data).collect(),
+        }
+    }
+
+    fn fail_receiver(&self) {
+        self.stalled.set(true);
+        self.wake_receiver();
+    }
+}
+
+pub(crate) fn channel<'scope, T>(capacity: usize) -> (Sender<'scope, T>, Receiver<'scope, T>) {
+    assert!(capacity > 0, "channel capacity must be non-zero");
+    let chan = Rc::new(Chan::new(capacity));
+    (
+        Sender {
+            chan: chan.clone(),
+            phantom: PhantomData,
+        },
+        Receiver {
+            chan,
+            phantom: PhantomData,
+        },
+    )
+}
+
+/// The sending half of a channel created with [`Scope::channel`][crate::Scope::channel].
+/// Senders can be cloned, one for each job that produces messages.
+///
+/// The channel closes once every sender has been dropped. A sender that is moved into a
+/// job is dropped as soon as that job finishes, so the channel closes once all of the
+/// producer jobs have finished.
+pub struct Sender<'scope, T> {
+    chan: Rc<Chan<T>>,
+    phantom: PhantomData<&'scope ()>,
+}
+
+impl<T> Sender<'_, T> {
+    /// Sends `value`, waiting for room in the channel's buffer if it is full. Fails,
+    /// returning the value, if the receiver has been dropped or the scope has been
+    /// terminated.
+    pub async fn send(&self, value: T) -> Result<(), SendError<T>> {
+        futures::future::poll_fn(|cx| self.chan.poll_ready(cx)).await;
+        self.chan.try_send(value)
+    }
+}
+
+impl<T> Clone for Sender<'_, T> {
+    fn clone(&self) -> Self {
+        self.chan.add_sender();
+        Self {
+            chan: self.chan.clone(),
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<T> Drop for Sender<'_, T> {
+    fn drop(&mut self) {
+        self.chan.drop_sender();
+    }
+}
+
+impl<T> fmt::Debug for Sender<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Sender").finish_non_exhaustive()
+    }
+}
+
+/// The receiving half of a channel created with [`Scope::channel`][crate::Scope::channel].
+pub struct Receiver<'scope, T> {
+    chan: Rc<Chan<T>>,
+    phantom: PhantomData<&'scope ()>,
+}
+
+impl<T> Receiver<'_, T> {
+    /// Receives the next message, waiting for one to be sent. Messages that were sent
+    /// before the channel closed are still received; after that, this returns
+    /// [`RecvError::Closed`].
+    ///
+    /// If no message can arrive because the scope has stalled, e.g. since the only
+    /// senders left are held by the job that waits here, this returns
+    /// [`RecvError::Stalled`] instead of waiting forever.
+    pub async fn recv(&mut self) -> Result<T, RecvError> {
+        futures::future::poll_fn(|cx| self.chan.poll_recv(cx)).await
+    }
+}
+
+impl<'scope, T: 'scope> Receiver<'scope, T> {
+    /// Lets the scope close the channel when it is terminated, and see who waits on it.
+    pub(crate) fn channel(&self) -> Weak<dyn Channel + 'scope> {
+        Rc::downgrade(&self.chan) as Weak<dyn Channel + 'scope>
+    }
+}
+
+impl<T> Drop for Receiver<'_, T> {
+    fn drop(&mut self) {
+        self.chan.close();
+    }
+}
+
+impl<T> fmt::Debug for Receiver<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Receiver").finish_non_exhaustive()
+    }
+}
+
+/// The sending half of a channel created with [`Scope::oneshot`][crate::Scope::oneshot].
+pub struct OneshotSender<'scope, T> {
+    sender: Sender<'scope, T>,
+}
+
+impl<T> OneshotSender<'_, T> {
+    /// Sends `value`. Fails, returning the value, if the receiver has been dropped or the
+    /// scope has been terminated.
+    pub fn send(self, value: T) -> Result<(), SendError<T>> {
+        self.sender.chan.try_send(value)
+    }
+}
+
+impl<T> fmt::Debug for OneshotSender<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OneshotSender").finish_non_exhaustive()
+    }
+}
+
+/// The receiving half of a channel created with [`Scope::oneshot`][crate::Scope::oneshot].
+/// Awaiting it yields the value, or an error like [`Receiver::recv`] does if the sender
+/// was dropped without sending.
+pub struct OneshotReceiver<'scope, T> {
+    receiver: Receiver<'scope, T>,
+}
+
+impl<T> Future for OneshotReceiver<'_, T> {
+    type Output = Result<T, RecvError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.receiver.chan.poll_recv(cx)
+    }
+}
+
+impl<T> fmt::Debug for OneshotReceiver<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OneshotReceiver").finish_non_exhaustive()
+    }
+}
+
+pub(crate) fn oneshot<'scope, T>() -> (OneshotSender<'scope, T>, OneshotReceiver<'scope, T>) {
+    let (sender, receiver) = channel(1);
+    (OneshotSender { sender }, OneshotReceiver { receiver })
+}
+
+impl<'scope, T: 'scope> OneshotReceiver<'scope, T> {
+    pub(crate) fn channel(&self) -> Weak<dyn Channel + 'scope> {
+        self.receiver.channel()
+    }
+}
+
+/// Returned by [`Sender::send`] when the channel is closed, along with the value that
+/// could not be sent.
+#[derive(Debug, PartialEq, Eq)]
+pub struct SendError<T>(pub T);
+
+impl<T> fmt::Display for SendError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "sending on a closed channel")
+    }
+}
+
+impl<T: fmt::Debug> std::error::Error for SendError<T> {}
+
+/// Returned by [`Receiver::recv`] when no more messages will arrive.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum RecvError {
+    /// Every sender has been dropped, or the scope has been terminated, and all of the
+    /// messages have been received.
+    Closed,
+
+    /// The scope stalled while the receiver waited: every job that still holds a sender
+    /// (or the scope body) waits on something that only the scope's stalled jobs could
+    /// provide. Typically a sender was left in the job that receives.
+    Stalled,
+}
+
+impl fmt::Display for RecvError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RecvError::Closed => write!(f, "receiving on a closed channel"),
+            RecvError::Stalled => write!(f, "receiving on a channel whose senders are stalled"),
+        }
+    }
+}
+
+impl std::error::Error for RecvError {}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        cell::{Cell, RefCell},
+        task::{Poll, Waker},
+    };
+
+    use futures::{executor::block_on, future::poll_fn};
+
+    use super::RecvError;
+
+    /// Yields to the executor once.
+    async fn yield_once() {
+        let mut yielded = false;
+        poll_fn(|cx| {
+            if std::mem::replace(&mut yielded, true) {
+                Poll::Ready(())
+            } else {
+                cx.waker().wake_by_ref();
+                Poll::Pending
+            }
+        })
+        .await
+    }
+
+    #[test]
+    fn recv_fails_once_the_senders_are_stalled() {
+        let result = block_on(crate::infallible_scope!(|scope| {
+            let consumer = scope.spawn(async {
+                let (sender, mut receiver) = scope.channel(1);
+                let producer = sender.clone();
+                scope.spawn(async move { producer.send(1).await.unwrap() });
+                let first = receiver.recv().await;
+                let second = receiver.recv().await;
+                drop(sender);
+                (first, second)
+            });
+            consumer.output().await
+        }));
+        assert_eq!(result, (Ok(1), Err(RecvError::Stalled)));
+    }
+
+    #[test]
+    fn recv_fails_one_receiver_at_a_time() {
+        let result = block_on(crate::infallible_scope!(|scope| {
+            let (sender, mut receiver) = scope.channel::<u32>(1);
+            let (forwarder, mut forwarded) = scope.channel(1);
+            scope.spawn(async move {
+                // Once this fails, dropping `forwarder` closes the other channel.
+                let _forwarder = forwarder;
+                receiver.recv().await
+            });
+            let result = forwarded.recv().await;
+            drop(sender);
+            result
+        }));
+        assert_eq!(result, Err::<u32, _>(RecvError::Closed));
+    }
+
+    #[test]
+    fn recv_waits_while_a_sender_can_be_woken() {
+        let ready = Cell::new(false);
+        let waker: RefCell<Option<Waker>> = Default::default();
+        let (ready, waker) = (&ready, &waker);
+        let scope = crate::infallible_scope!(|scope| {
+            let (sender, mut receiver) = scope.channel(1);
+            scope.spawn(async move {
+                poll_fn(|cx| {
+                    if ready.get() {
+                        return Poll::Ready(());
+                    }
+                    *waker.borrow_mut() = Some(cx.waker().clone());
+                    Poll::Pending
+                })
+                .await;
+                sender.send(1).await.unwrap();
+            });
+            receiver.recv().await
+        });
+        let wake = async {
+            for _ in 0..3 {
+                yield_once().await;
+            }
+            ready.set(true);
+            waker.take().unwrap().wake();
+        };
+        let (result, ()) = block_on(futures::future::join(scope, wake));
+        assert_eq!(result, Ok(1));
+    }
+}
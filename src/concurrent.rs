@@ -0,0 +1,263 @@
+use std::{collections::VecDeque, ops::ControlFlow, task::Poll};
+
+use futures::{future::poll_fn, Future};
+
+use crate::{Aborted, AsyncIterator, IntoAsyncIter, Scope, Spawned, Stream};
+
+/// The order in which [`Stream::map_concurrent`] produces its results.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum Order {
+    /// In the order of the items they were computed from.
+    #[default]
+    Ordered,
+
+    /// In the order the computations complete.
+    Unordered,
+}
+
+/// Takes an output from `jobs` that is already available, if any: the first job's if
+/// `order` is [`Order::Ordered`], otherwise any job's.
+fn take_ready<T>(jobs: &mut VecDeque<Spawned<'_, T>>, order: Order) -> Option<T> {
+    let candidates = match order {
+        Order::Ordered => jobs.len().min(1),
+        Order::Unordered => jobs.len(),
+    };
+    let (index, output) = (0..candidates).find_map(|i| Some((i, jobs[i].try_take_output()?)))?;
+    jobs.remove(index);
+    Some(output)
+}
+
+/// Waits for an output from `jobs`: the first job's if `order` is [`Order::Ordered`],
+/// otherwise whichever job completes first. Jobs that were aborted have no output and
+/// are skipped. Returns `None` once `jobs` is empty.
+async fn next_output<T>(jobs: &mut VecDeque<Spawned<'_, T>>, order: Order) -> Option<T> {
+    poll_fn(|cx| {
+        let mut index = 0;
+        while index < jobs.len() {
+            match jobs[index].poll_result(cx) {
+                Poll::Ready(Ok(output)) => {
+                    jobs.remove(index);
+                    return Poll::Ready(Some(output));
+                }
+                Poll::Ready(Err(Aborted)) => {
+                    jobs.remove(index);
+                }
+                Poll::Pending if order == Order::Ordered => return Poll::Pending,
+                Poll::Pending => index += 1,
+            }
+        }
+        Poll::Ready(None)
+    })
+    .await
+}
+
+pub(crate) async fn for_each_concurrent<'scope, S, F>(
+    stream: &mut S,
+    scope: &'scope Scope<'scope, '_>,
+    limit: usize,
+    mut op: impl FnMut(S::Item) -> F,
+) where
+    S: Stream,
+    F: Future<Output = ()> + 'scope,
+{
+    assert!(limit > 0, "concurrency limit must be at least 1");
+    let mut running = stream
+        .fold(VecDeque::new(), async |mut running, item| {
+            if running.len() >= limit {
+                next_output(&mut running, Order::Unordered).await;
+            }
+            running.push_back(scope.spawn(op(item)));
+            running
+        })
+        .await;
+    while next_output(&mut running, Order::Unordered).await.is_some() {}
+}
+
+pub(crate) struct MapConcurrent<'scope, 'env, S, O> {
+    stream: S,
+    scope: &'scope Scope<'scope, 'env>,
+    limit: usize,
+    order: Order,
+    op: O,
+}
+
+impl<'scope, 'env, S, O> MapConcurrent<'scope, 'env, S, O> {
+    pub(crate) fn new(
+        stream: S,
+        scope: &'scope Scope<'scope, 'env>,
+        limit: usize,
+        order: Order,
+        op: O,
+    ) -> Self {
+        assert!(limit > 0, "concurrency limit must be at least 1");
+        Self {
+            stream,
+            scope,
+            limit,
+            order,
+            op,
+        }
+    }
+}
+
+impl<'scope, S, O, F, U> Stream for MapConcurrent<'scope, '_, S, O>
+where
+    S: Stream,
+    O: FnMut(S::Item) -> F,
+    F: Future<Output = U> + 'scope,
+    U: 'scope,
+{
+    async fn try_fold<R>(
+        &mut self,
+        start: R,
+        mut op: impl async FnMut(R, Self::Item) -> ControlFlow<R, R>,
+    ) -> ControlFlow<R, R> {
+        let (scope, limit, order) = (self.scope, self.limit, self.order);
+        let map_op = &mut self.op;
+        let mut running = VecDeque::new();
+        let flow = self
+            .stream
+            .try_fold(start, async |mut acc, item| {
+                while let Some(output) = take_ready(&mut running, order) {
+                    acc = op(acc, output).await?;
+                }
+                if running.len() >= limit {
+                    if let Some(output) = next_output(&mut running, order).await {
+                        acc = op(acc, output).await?;
+                    }
+                }
+                running.push_back(scope.spawn(map_op(item)));
+                ControlFlow::Continue(acc)
+            })
+            .await;
+        let flow = match flow {
+            ControlFlow::Continue(mut acc) => loop {
+                let Some(output) = next_output(&mut running, order).await else {
+                    break ControlFlow::Continue(acc);
+                };
+                match op(acc, output).await {
+                    ControlFlow::Continue(next) => acc = next,
+                    flow => break flow,
+                }
+            },
+            flow => flow,
+        };
+
+        // Nobody wants the results of jobs still running after an early stop.
+        for job in running {
+            job.abort();
+        }
+        flow
+    }
+}
+
+impl<'scope, S, O, F, U> IntoAsyncIter for MapConcurrent<'scope, '_, S, O>
+where
+    S: Stream,
+    O: FnMut(S::Item) -> F,
+    F: Future<Output = U> + 'scope,
+    U: 'scope,
+{
+    type Item = U;
+
+    fn into_async_iter(self, scope: &Scope<'_, '_>) -> impl AsyncIterator<Item = Self::Item> {
+        MapConcurrentIter {
+            iter: self.stream.into_async_iter(scope),
+            scope: self.scope,
+            limit: self.limit,
+            order: self.order,
+            op: self.op,
+            running: VecDeque::new(),
+            exhausted: false,
+        }
+    }
+}
+
+struct MapConcurrentIter<'scope, 'env, I, O, U> {
+    iter: I,
+    scope: &'scope Scope<'scope, 'env>,
+    limit: usize,
+    order: Order,
+    op: O,
+    running: VecDeque<Spawned<'scope, U>>,
+    exhausted: bool,
+}
+
+impl<'scope, I, O, F, U> AsyncIterator for MapConcurrentIter<'scope, '_, I, O, U>
+where
+    I: AsyncIterator,
+    O: FnMut(I::Item) -> F,
+    F: Future<Output = U> + 'scope,
+    U: 'scope,
+{
+    type Item = U;
+
+    async fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            while !self.exhausted && self.running.len() < self.limit {
+                match self.iter.next().await {
+                    Some(item) => self.running.push_back(self.scope.spawn((self.op)(item))),
+                    None => self.exhausted = true,
+                }
+            }
+            let output = next_output(&mut self.running, self.order).await;
+            if output.is_some() || self.exhausted {
+                return output;
+            }
+        }
+    }
+}
+
+impl<I, O, U> Drop for MapConcurrentIter<'_, '_, I, O, U> {
+    fn drop(&mut self) {
+        // As in `try_fold`, nobody wants the results of the jobs still running.
+        for job in self.running.drain(..) {
+            job.abort();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use futures::{executor::block_on, future::pending};
+
+    use crate::{AsyncIterator, IntoAsyncIter, Order, Stream};
+
+    /// Counts the jobs whose futures have been dropped, finished or not.
+    struct DropCount<'a>(&'a Cell<usize>);
+
+    impl Drop for DropCount<'_> {
+        fn drop(&mut self) {
+            self.0.set(self.0.get() + 1);
+        }
+    }
+
+    #[test]
+    fn dropping_map_concurrent_iter_aborts_running_jobs() {
+        let dropped = Cell::new(0);
+        let first = block_on(crate::infallible_scope!(|scope| {
+            let mut iter = crate::source::range(0..3)
+                .map_concurrent(scope, 3, Order::Unordered, |n| {
+                    let guard = DropCount(&dropped);
+                    async move {
+                        let _guard = guard;
+                        if n > 0 {
+                            pending::<()>().await;
+                        }
+                        n
+                    }
+                })
+                .into_async_iter(scope);
+            let first = iter.next().await;
+            assert_eq!(dropped.get(), 1);
+
+            // The two jobs that are still pending are aborted, so the scope can end.
+            drop(iter);
+            assert_eq!(dropped.get(), 3);
+            first
+        }));
+        assert_eq!(first, Some(0));
+    }
+}
@@ -0,0 +1,160 @@
+use std::{
+    fmt::{self, Write},
+    panic::Location,
+    time::{Duration, Instant},
+};
+
+use crate::registry::{JobRecord, Kind, Status};
+
+/// A snapshot of the jobs in a scope, taken with [`Scope::dump`][crate::Scope::dump].
+///
+/// The jobs form a tree: each job is listed under the job (or scope body) that spawned
+/// it. A nested scope is listed under the job whose future created it, with its own
+/// jobs under it in turn. Each entry shows where the job was spawned (or the scope
+/// created), its state (for a scope, that of its body), how often it was polled, and how
+/// long ago it was last polled.
+///
+/// [`Display`](fmt::Display) renders the tree as indented text, and
+/// [`to_json`](Self::to_json) renders it as JSON.
+#[derive(Clone, Debug)]
+pub struct ScopeDump {
+    root: Node,
+}
+
+#[derive(Clone, Debug)]
+struct Node {
+    kind: Kind,
+    name: Option<String>,
+    location: Option<&'static Location<'static>>,
+    status: Status,
+    polls: u64,
+    since_last_poll: Option<Duration>,
+    children: Vec<Node>,
+}
+
+impl ScopeDump {
+    pub(crate) fn new(record: &JobRecord) -> Self {
+        Self {
+            root: Node::new(record, Instant::now()),
+        }
+    }
+
+    /// Renders the tree as JSON. Each job is an object with the fields `kind`
+    /// (`"scope"`, `"job"` or `"finalizers"`), `id` (the job's position in its scope's
+    /// spawn order, or `null` for a scope), `name`, `location`, `state`, `polls`,
+    /// `since_last_poll_ms` and `children`.
+    pub fn to_json(&self) -> String {
+        let mut json = String::new();
+        self.root.write_json(&mut json);
+        json
+    }
+}
+
+impl fmt::Display for ScopeDump {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.root.write_text(f, 0)
+    }
+}
+
+impl Node {
+    fn new(record: &JobRecord, now: Instant) -> Self {
+        let mut children: Vec<_> = record
+            .children
+            .borrow()
+            .iter()
+            .filter_map(|child| child.upgrade())
+            .collect();
+        children.sort_by_key(|child| child.key);
+        Self {
+            kind: record.kind,
+            name: record.name.clone(),
+            location: record.location,
+            status: record.status.get(),
+            polls: record.polls.get(),
+            since_last_poll: record.last_poll.get().map(|last| now - last),
+            children: children.iter().map(|child| Node::new(child, now)).collect(),
+        }
+    }
+
+    fn write_text(&self, f: &mut fmt::Formatter<'_>, depth: usize) -> fmt::Result {
+        write!(f, "{:indent$}", "", indent = depth * 2)?;
+        match self.kind {
+            Kind::Body => write!(f, "scope")?,
+            Kind::Job(id) => write!(f, "job {id}")?,
+            Kind::Finalizers(id) => write!(f, "job {id} (finalizers)")?,
+        }
+        if let Some(name) = &self.name {
+            write!(f, " {name:?}")?;
+        }
+        if let Some(location) = self.location {
+            write!(f, " at {location}")?;
+        }
+        write!(f, ": {}", self.status.as_str())?;
+        match (self.polls, self.since_last_poll) {
+            (1, Some(since)) => write!(f, ", polled once, {since:?} ago")?,
+            (polls, Some(since)) => write!(f, ", polled {polls} times, last {since:?} ago")?,
+            (_, None) => write!(f, ", never polled")?,
+        }
+        writeln!(f)?;
+        for child in &self.children {
+            child.write_text(f, depth + 1)?;
+        }
+        Ok(())
+    }
+
+    fn write_json(&self, json: &mut String) {
+        let (kind, id) = match self.kind {
+            Kind::Body => ("scope", None),
+            Kind::Job(id) => ("job", Some(id)),
+            Kind::Finalizers(id) => ("finalizers", Some(id)),
+        };
+        json.push_str(r#"{"kind":"#);
+        write_json_string(json, kind);
+        json.push_str(r#","id":"#);
+        match id {
+            Some(id) => write!(json, "{id}").unwrap(),
+            None => json.push_str("null"),
+        }
+        json.push_str(r#","name":"#);
+        match &self.name {
+            Some(name) => write_json_string(json, name),
+            None => json.push_str("null"),
+        }
+        json.push_str(r#","location":"#);
+        match self.location {
+            Some(location) => write_json_string(json, &location.to_string()),
+            None => json.push_str("null"),
+        }
+        json.push_str(r#","state":"#);
+        write_json_string(json, self.status.as_str());
+        write!(json, r#","polls":{},"since_last_poll_ms":"#, self.polls).unwrap();
+        match self.since_last_poll {
+            Some(since) => write!(json, "{}", since.as_secs_f64() * 1000.0).unwrap(),
+            None => json.push_str("null"),
+        }
+        json.push_str(r#","children":["#);
+        for (i, child) in self.children.iter().enumerate() {
+            if i > 0 {
+                json.push(',');
+            }
+            child.write_json(json);
+        }
+        json.push_str("]}");
+    }
+}
+
+fn write_json_string(json: &mut String, s: &str) {
+    json.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if c.is_control() => write!(json, "\\u{:04x}", c as u32).unwrap(),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+}
@@ -0,0 +1,76 @@
+use std::error::Error;
+
+use futures::{future::LocalBoxFuture, Future, FutureExt};
+
+use crate::job::SharedState;
+
+/// The error reported by a finalizer registered with [`Scope::defer`][crate::Scope::defer]
+/// or [`Spawned::defer`][crate::Spawned::defer] (or their `on_cancel` variants).
+pub type FinalizerError = Box<dyn Error>;
+
+/// The output of a finalizer: either `()`, or a `Result` whose error is reported with
+/// [`ScopeBody::on_finalizer_error`][crate::ScopeBody::on_finalizer_error] or
+/// [`ScopeBody::with_finalizer_errors`][crate::ScopeBody::with_finalizer_errors].
+pub trait FinalizerOutput {
+    fn into_result(self) -> Result<(), FinalizerError>;
+}
+
+impl FinalizerOutput for () {
+    fn into_result(self) -> Result<(), FinalizerError> {
+        Ok(())
+    }
+}
+
+impl<E> FinalizerOutput for Result<(), E>
+where
+    E: Into<FinalizerError>,
+{
+    fn into_result(self) -> Result<(), FinalizerError> {
+        self.map_err(Into::into)
+    }
+}
+
+/// A registered finalizer.
+pub(crate) struct Finalizer<'scope> {
+    /// True if the finalizer only runs when its job or scope is cancelled.
+    on_cancel: bool,
+    future: LocalBoxFuture<'scope, Result<(), FinalizerError>>,
+}
+
+impl<'scope> Finalizer<'scope> {
+    pub(crate) fn new<O>(on_cancel: bool, future: impl Future<Output = O> + 'scope) -> Self
+    where
+        O: FinalizerOutput + 'scope,
+    {
+        Self {
+            on_cancel,
+            future: Box::pin(future.map(FinalizerOutput::into_result)),
+        }
+    }
+}
+
+/// Returns a future that runs `finalizers` one after the other, most recently registered
+/// first, reporting their errors to `shared`. Finalizers that only run on cancellation
+/// are skipped unless `cancelled` is true. Returns `None` if there is nothing to run.
+pub(crate) fn run_finalizers<'scope>(
+    finalizers: Vec<Finalizer<'scope>>,
+    cancelled: bool,
+    shared: &'scope SharedState<'scope>,
+) -> Option<LocalBoxFuture<'scope, ()>> {
+    let futures: Vec<_> = finalizers
+        .into_iter()
+        .rev()
+        .filter(|finalizer| cancelled || !finalizer.on_cancel)
+        .map(|finalizer| finalizer.future)
+        .collect();
+    if futures.is_empty() {
+        return None;
+    }
+    Some(Box::pin(async move {
+        for future in futures {
+            if let Err(error) = future.await {
+                shared.report_finalizer_error(error);
+            }
+        }
+    }))
+}
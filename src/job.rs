@@ -0,0 +1,604 @@
+use std::{
+    any::Any,
+    cell::{Cell, RefCell},
+    cmp::{self, Reverse},
+    panic::{AssertUnwindSafe, Location},
+    pin::Pin,
+    rc::Rc,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    task::{Context, Poll},
+};
+
+use futures::{
+    future::LocalBoxFuture,
+    task::{waker_ref, ArcWake, AtomicWaker},
+    Future,
+};
+
+use crate::{
+    finalizer::{run_finalizers, Finalizer, FinalizerError},
+    registry::{JobRecord, Kind, Polling, Status},
+    trace, Aborted,
+};
+
+/// A spawned job, as stored in the scope's `FuturesUnordered`, along with its
+/// position in spawn order.
+///
+/// The job's future itself lives in a [`JobCell`] that is shared with the job's
+/// [`Spawned`][crate::Spawned] handle, so that either side can drive it.
+pub(crate) struct Job<'scope> {
+    pub(crate) id: usize,
+    pub(crate) shielded: bool,
+
+    /// See [`JobBuilder::priority`][crate::JobBuilder::priority].
+    pub(crate) priority: i32,
+    pub(crate) cell: Rc<dyn ErasedJob + 'scope>,
+}
+
+impl Job<'_> {
+    /// The order in which enqueued jobs are admitted, greatest first: by priority, and
+    /// then in spawn order.
+    fn admission_key(&self) -> (i32, Reverse<usize>) {
+        (self.priority, Reverse(self.id))
+    }
+}
+
+impl PartialEq for Job<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
+impl Eq for Job<'_> {}
+
+impl PartialOrd for Job<'_> {
+    fn partial_cmp(&self, other: &Self) -> Option<cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Job<'_> {
+    fn cmp(&self, other: &Self) -> cmp::Ordering {
+        self.admission_key().cmp(&other.admission_key())
+    }
+}
+
+/// What the scope needs to know about a job (or scope body) that is pending, to tell
+/// whether it has stalled.
+pub(crate) struct Waiting {
+    /// Set if it was woken since it was last polled.
+    pub(crate) woken: bool,
+
+    /// The number of clones of its waker that are held somewhere.
+    pub(crate) wakers: usize,
+
+    /// The data pointer of its waker, to recognize the clones held by channels and by
+    /// the handles of other jobs.
+    pub(crate) waker: *const (),
+
+    /// The data pointer of the waker registered with its handle, if any.
+    pub(crate) waiter: Option<*const ()>,
+}
+
+/// Reported by a [`Job`] once it is no longer running.
+pub(crate) struct Finished {
+    pub(crate) shielded: bool,
+
+    /// Payload of the job's panic, if it panicked.
+    pub(crate) panic: Option<Box<dyn Any + Send>>,
+}
+
+impl Future for Job<'_> {
+    type Output = Finished;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Finished> {
+        self.cell.poll_entry(cx).map(|panic| Finished {
+            shielded: self.shielded,
+            panic,
+        })
+    }
+}
+
+impl Drop for Job<'_> {
+    fn drop(&mut self) {
+        // The handle may outlive the scope's entry for the job, but the job's
+        // future must not: see `Scope::clear`.
+        self.cell.discard();
+    }
+}
+
+/// The parts of a [`JobCell`] that do not depend on the job's output type.
+pub(crate) trait ErasedJob {
+    /// Polls the job on behalf of the scope. Returns `Ready` once the job is no
+    /// longer running, along with its panic payload if it panicked.
+    fn poll_entry(&self, cx: &mut Context<'_>) -> Poll<Option<Box<dyn Any + Send>>>;
+
+    /// Drops the job's future, unless the job has already finished. Its finalizers
+    /// are scheduled to run.
+    fn abort(&self);
+
+    /// Drops the job's future and its finalizers, without running them.
+    fn discard(&self);
+
+    /// True if the job is no longer running.
+    fn is_finished(&self) -> bool;
+
+    /// True if finalizers were registered for the job.
+    fn has_finalizers(&self) -> bool;
+
+    /// Marks the job as admitted by the scope's concurrency limit.
+    fn admit(&self);
+
+    /// Describes the job, to tell whether it has stalled, unless it has finished.
+    fn waiting(&self) -> Option<Waiting>;
+
+    /// See [`JobBuilder::name`][crate::JobBuilder::name].
+    fn name(&self) -> Option<&str>;
+}
+
+/// The state of a scope that the cells of its jobs need as well.
+#[derive(Default)]
+pub(crate) struct SharedState<'scope> {
+    /// Set once the scope is terminated, after which only shielded jobs run.
+    pub(crate) terminated: Cell<bool>,
+
+    /// See [`ScopeBody::with_deadlock_detection`][crate::ScopeBody::with_deadlock_detection].
+    pub(crate) detect_deadlocks: Cell<bool>,
+
+    /// Set if jobs are recorded in the task tree; see
+    /// [`ScopeBody::with_task_tree`][crate::ScopeBody::with_task_tree].
+    pub(crate) record_tasks: Cell<bool>,
+
+    /// Set to stop the job (or scope body) that is being polled, without terminating
+    /// the scope. See [`ErrorMode::CollectAll`][crate::ErrorMode::CollectAll].
+    pub(crate) stop_current: Cell<bool>,
+
+    /// Counter used to assign each job its spawn order.
+    next_job_id: Cell<usize>,
+
+    /// Shielded jobs that run finalizers, waiting for the scope to start them.
+    pub(crate) finalizers: RefCell<Vec<Job<'scope>>>,
+
+    /// Errors reported by finalizers, unless `finalizer_error_handler` is set.
+    pub(crate) finalizer_errors: RefCell<Vec<FinalizerError>>,
+
+    /// See [`ScopeBody::on_finalizer_error`][crate::ScopeBody::on_finalizer_error].
+    pub(crate) finalizer_error_handler: RefCell<Option<FinalizerErrorHandler<'scope>>>,
+}
+
+pub(crate) type FinalizerErrorHandler<'scope> = Box<dyn FnMut(FinalizerError) + 'scope>;
+
+impl<'scope> SharedState<'scope> {
+    /// Assigns the next job its position in spawn order.
+    pub(crate) fn next_job_id(&self) -> usize {
+        let id = self.next_job_id.get();
+        self.next_job_id.set(id + 1);
+        id
+    }
+
+    /// Schedules a shielded job that runs `finalizers`; see [`run_finalizers`].
+    pub(crate) fn schedule_finalizers(
+        &'scope self,
+        finalizers: Vec<Finalizer<'scope>>,
+        cancelled: bool,
+    ) {
+        if let Some(future) = run_finalizers(finalizers, cancelled, self) {
+            let id = self.next_job_id();
+            let job = Job {
+                id,
+                shielded: true,
+                priority: 0,
+                cell: Rc::new(JobCell::new(
+                    future,
+                    true,
+                    true,
+                    self,
+                    Kind::Finalizers(id),
+                    None,
+                    None,
+                )),
+            };
+            self.finalizers.borrow_mut().push(job);
+        }
+    }
+
+    pub(crate) fn report_finalizer_error(&self, error: FinalizerError) {
+        let handler = self.finalizer_error_handler.take();
+        match handler {
+            Some(mut handler) => {
+                handler(error);
+                *self.finalizer_error_handler.borrow_mut() = Some(handler);
+            }
+            None => self.finalizer_errors.borrow_mut().push(error),
+        }
+    }
+}
+
+/// State shared between a job's entry in the scope and its [`Spawned`][crate::Spawned]
+/// handle: the job's future while it runs, and then its result.
+pub(crate) struct JobCell<'scope, T> {
+    /// The job's entry in the task tree, if the scope records one.
+    record: Option<Rc<JobRecord>>,
+
+    /// See [`JobBuilder::name`][crate::JobBuilder::name].
+    name: Option<String>,
+
+    /// Entered while the job is polled, with the `tracing` feature.
+    span: trace::Span,
+
+    state: RefCell<JobState<'scope, T>>,
+
+    /// False while the job waits for the scope's concurrency limit; until then,
+    /// the handle may not drive it.
+    admitted: Cell<bool>,
+
+    shielded: bool,
+
+    scope: &'scope SharedState<'scope>,
+
+    /// See [`JobBuilder::deadline`][crate::JobBuilder::deadline]: once this sleep
+    /// completes, the job is aborted.
+    deadline: RefCell<Option<LocalBoxFuture<'static, ()>>>,
+
+    /// Registered with [`Spawned::defer`][crate::Spawned::defer] and
+    /// [`Spawned::on_cancel`][crate::Spawned::on_cancel].
+    finalizers: RefCell<Vec<Finalizer<'scope>>>,
+
+    /// Wakers must be `Send` and `Sync`, so unlike the rest of the scope these use
+    /// atomics.
+    wakers: Arc<JobWakers>,
+
+    /// The data pointer of the waker registered with `wakers.handle`.
+    waiter: Cell<Option<*const ()>>,
+}
+
+/// The tasks interested in a job: the scope, which drives every job, and the task
+/// awaiting the job's handle, if any.
+#[derive(Default)]
+pub(crate) struct JobWakers {
+    pub(crate) entry: AtomicWaker,
+    handle: AtomicWaker,
+
+    /// Set when woken, and cleared when the job is polled.
+    woken: AtomicBool,
+}
+
+impl JobWakers {
+    /// Clears `woken` before the job (or scope body) is polled.
+    pub(crate) fn start_poll(&self) {
+        self.woken.store(false, Ordering::Relaxed);
+    }
+
+    /// Describes the job (or scope body) polled with these wakers, to tell whether it
+    /// has stalled. Clones of the waker are counted by their references to the `Arc`.
+    pub(crate) fn waiting(self: &Arc<Self>, waiter: Option<*const ()>) -> Waiting {
+        Waiting {
+            woken: self.woken.load(Ordering::Relaxed),
+            wakers: Arc::strong_count(self) - 1,
+            waker: Arc::as_ptr(self).cast(),
+            waiter,
+        }
+    }
+}
+
+impl ArcWake for JobWakers {
+    /// The job is polled with this waker, both by the scope and by the handle when it
+    /// drives the job, so that the scope keeps driving it if the handle stops being
+    /// polled, and so that the scope can tell when it has stalled.
+    fn wake_by_ref(arc_self: &Arc<Self>) {
+        arc_self.woken.store(true, Ordering::Relaxed);
+        arc_self.entry.wake();
+        arc_self.handle.wake();
+    }
+}
+
+enum JobState<'scope, T> {
+    /// The job is running. The future is `None` while it is being polled.
+    Running(Option<LocalBoxFuture<'scope, T>>),
+
+    /// The job was aborted while it was being polled; the poller drops it.
+    AbortRequested,
+
+    Done(T),
+    Panicked(Option<Box<dyn Any + Send>>),
+    Aborted,
+
+    /// The job was stopped after recording an error with the scope.
+    Failed,
+    Taken,
+}
+
+impl<'scope, T> JobCell<'scope, T> {
+    pub(crate) fn new(
+        future: LocalBoxFuture<'scope, T>,
+        admitted: bool,
+        shielded: bool,
+        scope: &'scope SharedState<'scope>,
+        kind: Kind,
+        location: Option<&'static Location<'static>>,
+        name: Option<String>,
+    ) -> Self {
+        let status = if admitted {
+            Status::Pending
+        } else {
+            Status::Enqueued
+        };
+        Self {
+            span: trace::Span::new(kind, location, name.as_deref()),
+            record: scope
+                .record_tasks
+                .get()
+                .then(|| JobRecord::new(kind, status, location, name.clone())),
+            name,
+            state: RefCell::new(JobState::Running(Some(future))),
+            admitted: Cell::new(admitted),
+            shielded,
+            scope,
+            deadline: Default::default(),
+            finalizers: Default::default(),
+            wakers: Default::default(),
+            waiter: Default::default(),
+        }
+    }
+
+    /// Polls the job's future, if it is still running and not already being polled.
+    /// Returns `Ready` once the job is no longer running.
+    fn poll_job(&self, cx: &mut Context<'_>) -> Poll<()> {
+        let mut future = match &mut *self.state.borrow_mut() {
+            JobState::Running(future) => match future.take() {
+                Some(future) => future,
+                None => return Poll::Pending,
+            },
+            _ => return Poll::Ready(()),
+        };
+
+        self.wakers.start_poll();
+        if self.deadline_elapsed(cx) {
+            *self.state.borrow_mut() = JobState::Running(Some(future));
+            self.abort();
+            return Poll::Ready(());
+        }
+
+        let polling = self.record.as_deref().map(Polling::enter);
+        let result = self
+            .span
+            .in_scope(|| std::panic::catch_unwind(AssertUnwindSafe(|| future.as_mut().poll(cx))));
+        drop(polling);
+
+        let stopped = self.scope.stop_current.take();
+        let mut state = self.state.borrow_mut();
+        let next = match result {
+            Ok(Poll::Pending) if matches!(*state, JobState::AbortRequested) => JobState::Aborted,
+            Ok(Poll::Pending) if stopped => JobState::Failed,
+            Ok(Poll::Pending) => {
+                *state = JobState::Running(Some(future));
+                self.set_status(Status::Pending);
+                return Poll::Pending;
+            }
+            Ok(Poll::Ready(v)) => JobState::Done(v),
+            Err(payload) => JobState::Panicked(Some(match &self.name {
+                Some(name) => name_panic(payload, name),
+                None => payload,
+            })),
+        };
+
+        let status = match next {
+            JobState::Done(_) => Status::Done,
+            JobState::Panicked(_) => Status::Panicked,
+            JobState::Failed => Status::Failed,
+            _ => Status::Aborted,
+        };
+        // A job that aborted itself while being polled was already finished by `abort`.
+        if matches!(*state, JobState::AbortRequested) {
+            self.set_status(status);
+        } else {
+            self.finish(status);
+        }
+        let cancelled = !matches!(next, JobState::Done(_));
+        *state = next;
+        drop(state);
+
+        // Drop the future outside of the borrow, since dropping it runs arbitrary code.
+        drop(future);
+        self.finalize(cancelled);
+        Poll::Ready(())
+    }
+
+    /// Aborts the job once `sleep` completes, unless it has finished by then.
+    pub(crate) fn set_deadline(&self, sleep: LocalBoxFuture<'static, ()>) {
+        *self.deadline.borrow_mut() = Some(sleep);
+    }
+
+    /// Polls the job's deadline, if it has one.
+    fn deadline_elapsed(&self, cx: &mut Context<'_>) -> bool {
+        match &mut *self.deadline.borrow_mut() {
+            Some(sleep) => sleep.as_mut().poll(cx).is_ready(),
+            None => false,
+        }
+    }
+
+    /// Records that the job is no longer running.
+    fn finish(&self, status: Status) {
+        self.span.finished(status);
+        self.set_status(status);
+        self.deadline.take();
+    }
+
+    /// Updates the job's status in the task tree, if the scope records one.
+    fn set_status(&self, status: Status) {
+        if let Some(record) = &self.record {
+            record.status.set(status);
+        }
+    }
+
+    /// Schedules the job's finalizers, now that it has finished.
+    fn finalize(&self, cancelled: bool) {
+        let finalizers = self.finalizers.take();
+        if !finalizers.is_empty() {
+            self.scope.schedule_finalizers(finalizers, cancelled);
+        }
+    }
+
+    /// Registers a finalizer for the job. If the job has already finished, the finalizer
+    /// is scheduled right away.
+    pub(crate) fn add_finalizer(&self, finalizer: Finalizer<'scope>) {
+        self.finalizers.borrow_mut().push(finalizer);
+        let cancelled = match *self.state.borrow() {
+            JobState::Running(_) | JobState::AbortRequested => return,
+            JobState::Done(_) | JobState::Taken => false,
+            JobState::Panicked(_) | JobState::Aborted | JobState::Failed => true,
+        };
+        self.finalize(cancelled);
+    }
+
+    /// True if the handle may drive the job itself.
+    fn can_drive(&self) -> bool {
+        self.admitted.get() && (self.shielded || !self.scope.terminated.get())
+    }
+
+    /// Polls for the job's output on behalf of the handle, driving the job if it is
+    /// still running.
+    ///
+    /// If the job failed, the error was already recorded with the scope, so the task
+    /// awaiting the handle is stopped as well.
+    pub(crate) fn poll_output(&self, cx: &mut Context<'_>) -> Poll<Result<T, Aborted>> {
+        self.wakers.handle.register(cx.waker());
+        self.waiter.set(Some(cx.waker().data()));
+        loop {
+            let mut state = self.state.borrow_mut();
+            match std::mem::replace(&mut *state, JobState::Taken) {
+                JobState::Done(v) => return Poll::Ready(Ok(v)),
+                JobState::Aborted => {
+                    *state = JobState::Aborted;
+                    return Poll::Ready(Err(Aborted));
+                }
+                JobState::Failed => {
+                    *state = JobState::Failed;
+                    self.scope.stop_current.set(true);
+                    return Poll::Pending;
+                }
+                JobState::Taken => panic!("output of job was already taken"),
+                other => *state = other,
+            }
+
+            // If the job panicked, the scope resumes the panic, so there is nothing
+            // to wait for.
+            let running = matches!(*state, JobState::Running(Some(_)));
+            drop(state);
+            if !running || !self.can_drive() {
+                return Poll::Pending;
+            }
+
+            let waker = waker_ref(&self.wakers);
+            ready!(self.poll_job(&mut Context::from_waker(&waker)));
+
+            // Let the scope drop its entry for the job.
+            self.wakers.entry.wake();
+        }
+    }
+
+    /// Takes the job's output if it has completed.
+    pub(crate) fn try_take_output(&self) -> Option<T> {
+        let mut state = self.state.borrow_mut();
+        match std::mem::replace(&mut *state, JobState::Taken) {
+            JobState::Done(v) => Some(v),
+            other => {
+                *state = other;
+                None
+            }
+        }
+    }
+}
+
+impl<T> ErasedJob for JobCell<'_, T> {
+    fn poll_entry(&self, cx: &mut Context<'_>) -> Poll<Option<Box<dyn Any + Send>>> {
+        self.wakers.entry.register(cx.waker());
+        // Poll with the job's own waker, so that its clones can be counted.
+        let waker = waker_ref(&self.wakers);
+        ready!(self.poll_job(&mut Context::from_waker(&waker)));
+        self.wakers.handle.wake();
+        match &mut *self.state.borrow_mut() {
+            JobState::Panicked(payload) => Poll::Ready(payload.take()),
+            _ => Poll::Ready(None),
+        }
+    }
+
+    fn abort(&self) {
+        let future = {
+            let mut state = self.state.borrow_mut();
+            match &mut *state {
+                JobState::Running(Some(_)) => {
+                    match std::mem::replace(&mut *state, JobState::Aborted) {
+                        JobState::Running(future) => future,
+                        _ => unreachable!(),
+                    }
+                }
+                JobState::Running(None) => {
+                    *state = JobState::AbortRequested;
+                    None
+                }
+                _ => return,
+            }
+        };
+
+        self.finish(Status::Aborted);
+
+        // Drop the future outside of the borrow, since dropping it runs arbitrary code.
+        drop(future);
+        self.finalize(true);
+        self.wakers.entry.wake();
+        self.wakers.handle.wake();
+    }
+
+    fn discard(&self) {
+        self.finalizers.take();
+        self.abort();
+    }
+
+    fn is_finished(&self) -> bool {
+        !matches!(
+            *self.state.borrow(),
+            JobState::Running(_) | JobState::AbortRequested
+        )
+    }
+
+    fn has_finalizers(&self) -> bool {
+        !self.finalizers.borrow().is_empty()
+    }
+
+    fn admit(&self) {
+        self.admitted.set(true);
+        if let Some(record) = &self.record {
+            if record.status.get() == Status::Enqueued {
+                record.status.set(Status::Pending);
+            }
+        }
+    }
+
+    fn waiting(&self) -> Option<Waiting> {
+        if self.is_finished() {
+            return None;
+        }
+        Some(self.wakers.waiting(self.waiter.get()))
+    }
+
+    fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+}
+
+/// Names the job in the message of its panic, so that the name shows up when the scope
+/// resumes the panic. Payloads other than messages are left as they are.
+fn name_panic(payload: Box<dyn Any + Send>, name: &str) -> Box<dyn Any + Send> {
+    let message = if let Some(message) = payload.downcast_ref::<&str>() {
+        message
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.as_str()
+    } else {
+        return payload;
+    };
+    Box::new(format!("job {name:?} panicked: {message}"))
+}
@@ -0,0 +1,151 @@
+use std::{panic::Location, time::Duration};
+
+use futures::Future;
+
+use crate::{Scope, Spawned};
+
+/// Spawns a job with options, such as a name or a deadline. Created with
+/// [`Scope::build_job`].
+///
+/// A job spawned without options behaves like one spawned with [`Scope::spawn`].
+///
+/// # Examples
+///
+/// ```rust
+/// # futures::executor::block_on(async {
+/// let dump = moro::infallible_scope!(|scope| {
+///     scope
+///         .build_job()
+///         .name("fetch-users")
+///         .spawn(futures::future::pending::<()>())
+///         .abort();
+///     scope.dump().unwrap()
+/// })
+/// .with_task_tree()
+/// .await;
+/// assert!(dump.to_string().lines().nth(1).unwrap().starts_with(r#"  job 0 "fetch-users" at "#));
+/// # });
+/// ```
+pub struct JobBuilder<'scope, 'env: 'scope> {
+    scope: &'scope Scope<'scope, 'env>,
+    options: JobOptions,
+}
+
+/// The options set with a [`JobBuilder`].
+#[derive(Default)]
+pub(crate) struct JobOptions {
+    pub(crate) name: Option<String>,
+    pub(crate) priority: i32,
+    pub(crate) deadline: Option<Duration>,
+}
+
+impl<'scope, 'env> JobBuilder<'scope, 'env> {
+    pub(crate) fn new(scope: &'scope Scope<'scope, 'env>) -> Self {
+        Self {
+            scope,
+            options: Default::default(),
+        }
+    }
+
+    /// Names the job. The name shows up in the scope's [dump](Scope::dump), in
+    /// [deadlock](crate::ScopeBody::with_deadlock_detection) reports, in the `tracing`
+    /// span of the job (with the `tracing` cargo feature), and in the message of the
+    /// job's panic, if it panics with one.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let payload = std::panic::catch_unwind(|| {
+    ///     futures::executor::block_on(moro::infallible_scope!(|scope| {
+    ///         scope.build_job().name("fetch-users").spawn(async { panic!("boom") }).await
+    ///     }))
+    /// })
+    /// .unwrap_err();
+    /// assert_eq!(
+    ///     payload.downcast_ref::<String>().unwrap(),
+    ///     r#"job "fetch-users" panicked: boom"#
+    /// );
+    /// ```
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.options.name = Some(name.into());
+        self
+    }
+
+    /// Sets the job's priority; the default is 0. When the scope's
+    /// [concurrency limit](crate::ScopeBody::with_max_concurrency) makes jobs wait in a
+    /// queue, jobs with a higher priority start first, and jobs with the same priority
+    /// start in the order they were spawned. Without a limit, every job starts right away
+    /// and the priority has no effect.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # futures::executor::block_on(async {
+    /// let log = std::cell::RefCell::new(vec![]);
+    /// moro::infallible_scope!(|scope| {
+    ///     scope.spawn(async { log.borrow_mut().push("normal") });
+    ///     scope.build_job().priority(1).spawn(async { log.borrow_mut().push("urgent") });
+    /// })
+    /// .with_max_concurrency(1)
+    /// .await;
+    /// assert_eq!(*log.borrow(), ["urgent", "normal"]);
+    /// # });
+    /// ```
+    pub fn priority(mut self, priority: i32) -> Self {
+        self.options.priority = priority;
+        self
+    }
+
+    /// Aborts the job if it has not completed within `duration` of being spawned, as
+    /// measured by the scope's [timer](crate::ScopeBody::with_timer). Unlike
+    /// [`Spawned::move_on_after`], this applies whether or not the job's handle is
+    /// awaited; awaiting the handle of a job that was aborted this way returns
+    /// `Err(Aborted)`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use std::time::Duration;
+    /// use futures::future::LocalBoxFuture;
+    ///
+    /// /// A timer whose deadlines have always passed already.
+    /// struct Expired;
+    ///
+    /// impl moro::time::Timer for Expired {
+    ///     fn sleep(&self, _: Duration) -> LocalBoxFuture<'static, ()> {
+    ///         Box::pin(async {})
+    ///     }
+    /// }
+    ///
+    /// # futures::executor::block_on(async {
+    /// let result = moro::async_scope!(|scope| {
+    ///     let job = scope
+    ///         .build_job()
+    ///         .deadline(Duration::from_secs(1))
+    ///         .spawn(futures::future::pending::<()>());
+    ///     job.await
+    /// })
+    /// .with_timer(Expired)
+    /// .await;
+    /// assert_eq!(result, Err(moro::Aborted));
+    /// # });
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// When the job is spawned, if the scope has no timer.
+    pub fn deadline(mut self, duration: Duration) -> Self {
+        self.options.deadline = Some(duration);
+        self
+    }
+
+    /// Spawns the job, like [`Scope::spawn`].
+    #[track_caller]
+    pub fn spawn<T>(self, future: impl Future<Output = T> + 'scope) -> Spawned<'scope, T>
+    where
+        T: 'scope,
+    {
+        self.scope
+            .spawn_job_with(future, false, Location::caller(), self.options)
+    }
+}
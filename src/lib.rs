@@ -1,24 +1,43 @@
-#![feature(async_closure)]
+#![feature(async_trait_bounds)]
 #![feature(async_fn_traits)]
 #![feature(unboxed_closures)]
 #![allow(async_fn_in_trait)]
 
-use std::ops::AsyncFnOnce;
+use std::{ops::AsyncFnOnce, rc::Rc};
 
 #[macro_use]
 mod macros;
 
 mod async_iter;
 mod body;
+mod cancel_scope;
+mod cancellable_scope;
+mod channel;
+mod concurrent;
+mod dump;
+mod finalizer;
+mod job;
+mod job_builder;
+mod merge;
+mod multi_error;
 pub mod prelude;
+mod race;
+mod registry;
 mod result_ext;
 mod scope;
 mod scope_body;
+pub mod source;
 mod spawned;
 mod stream;
+mod supervisor;
+pub mod time;
+mod trace;
 
-pub use async_iter::{AsyncIterator, IntoAsyncIter};
-pub use stream::Stream;
+pub use async_iter::{AsyncIterator, IntoAsyncIter, Peekable};
+pub use channel::{OneshotReceiver, OneshotSender, Receiver, RecvError, SendError, Sender};
+pub use concurrent::Order;
+pub use dump::ScopeDump;
+pub use stream::{select_all, Stream};
 
 /// Creates an async scope within which you can spawn jobs.
 /// This works much like the stdlib's
@@ -32,18 +51,24 @@ pub use stream::Stream;
 ///
 /// # Cancellable vs infallible scopes
 ///
-/// By default, moro scopes support *cancellation*,
+/// Scopes created with `async_scope!` support *cancellation*,
 /// which means that you can cancel the entire scope by invoking
-/// `scope.cancel(v)`. Cancellable scopes return a [`Result`] value
-/// whose error type is the type of `v`. If your scope does not use `cancel`,
-/// you will get compilation errors because the error type cannot be inferred!
+/// [`scope.terminate(v)`][`CancellableScope::terminate`]. The scope's result is then `v`
+/// instead of the value returned by the body, so the two must have the same type;
+/// typically this is a [`Result`] and errors are propagated with `terminate(Err(e))`.
+/// If your scope never terminates, the error type may be left unconstrained,
+/// and you will get compilation errors because it cannot be inferred!
 ///
-/// To avoid this, use the `infallible` method on the scope to convert it
-/// into a non-cancellable scope:
+/// To avoid this, use [`infallible_scope!`] to create a non-cancellable scope.
+/// Its body is given a plain [`Scope`], which has no `terminate`, and its result
+/// is simply the value returned by the body:
 ///
 /// ```rust
 /// # futures::executor::block_on(async {
-/// let scope = moro::async_scope!(|scope| {/* ... */}).await;
+/// let result = moro::infallible_scope!(|scope| {
+///     scope.spawn(async { 22 }).output().await
+/// }).await;
+/// assert_eq!(result, 22);
 /// # });
 /// ```
 ///
@@ -57,7 +82,7 @@ pub use stream::Stream;
 /// let r = 22;
 /// let scope = moro::async_scope!(|scope| {
 ///     // OK to refer to `r` here
-///     scope.spawn(async { r }).await
+///     scope.spawn(async { r }).output().await
 /// });
 /// let result = scope.await;
 /// assert_eq!(result, 22);
@@ -75,7 +100,7 @@ pub use stream::Stream;
 //
 ///     // NOT ok to refer to `r` now, because `r`
 ///     // is defined inside the scope
-///     scope.spawn(async { r }).await
+///     scope.spawn(async { r }).output().await
 /// });
 /// let result = scope.await;
 /// assert_eq!(result, 22);
@@ -87,18 +112,18 @@ pub use stream::Stream;
 /// ## Hello, world
 ///
 /// The following scope spawns one concurrent task which iterates over
-/// the vector `v` and sums its values; the [`infallible`][`Scope::infallible`]
-/// method is used to indicate that the scope is never cancelled:
+/// the vector `v` and sums its values; [`infallible_scope!`]
+/// is used to indicate that the scope is never cancelled:
 ///
 /// ```rust
 /// # futures::executor::block_on(async {
 /// let v = vec![1, 2, 3, 5];
-/// let scope = moro::async_scope!(|scope| {
+/// let scope = moro::infallible_scope!(|scope| {
 ///     let job = scope.spawn(async {
 ///         let r: i32 = v.iter().sum();
 ///         r
 ///     });
-///     job.await * 2
+///     job.output().await * 2
 /// });
 /// let result = scope.await;
 /// assert_eq!(result, 22);
@@ -121,6 +146,26 @@ pub use stream::Stream;
 /// # });
 /// ```
 ///
+/// ## Values that are not `Send`
+///
+/// A scope and its jobs all run on the task that awaits the scope, so neither the
+/// jobs' outputs nor the scope's result need to be `Send`:
+///
+/// ```rust
+/// # futures::executor::block_on(async {
+/// use moro::prelude::*;
+/// use std::rc::Rc;
+///
+/// let result = moro::async_scope!(|scope| -> Result<Rc<u32>, Rc<str>> {
+///     let shared = scope.spawn(async { Ok::<_, Rc<str>>(Rc::new(22)) });
+///     let shared = shared.or_cancel(scope).output().await;
+///     Ok(shared)
+/// })
+/// .await;
+/// assert_eq!(result, Ok(Rc::new(22)));
+/// # });
+/// ```
+///
 /// ## More
 ///
 /// For more examples, see the [examples] directory in the
@@ -144,45 +189,156 @@ macro_rules! async_scope {
     }};
 }
 
+/// Creates an infallible async scope: like [`async_scope!`], but the scope cannot be
+/// terminated early. The body is given a [`Scope`] rather than a [`CancellableScope`],
+/// and the scope's result is the value returned by the body.
+///
+/// ```rust
+/// # futures::executor::block_on(async {
+/// let v = vec![1, 2, 3];
+/// let result = moro::infallible_scope!(|scope| {
+///     let jobs: Vec<_> = v.iter().map(|i| scope.spawn(async move { i * 2 })).collect();
+///     let mut sum = 0;
+///     for job in jobs {
+///         sum += job.output().await;
+///     }
+///     sum
+/// }).await;
+/// assert_eq!(result, 12);
+/// # });
+/// ```
+///
+/// An infallible scope has no `terminate`:
+///
+/// ```rust,compile_fail,E0599
+/// # futures::executor::block_on(async {
+/// let result: u32 = moro::infallible_scope!(|scope| scope.terminate(22).await).await;
+/// # });
+/// ```
+#[macro_export]
+macro_rules! infallible_scope {
+    (|$scope:ident| -> $result:ty { $($body:tt)* }) => {{
+        $crate::infallible_scope_fn::<$result, _>(|$scope| {
+            let future = async { $($body)* };
+            Box::pin(future)
+        })
+    }};
+    (|$scope:ident| $body:expr) => {{
+        $crate::infallible_scope_fn(|$scope| {
+            let future = async { $body };
+            Box::pin(future)
+        })
+    }};
+}
+
+/// Races futures, which may be of different types but must have the same output, within a
+/// scope: `race!(scope, a, b, ...)` is [`scope.race(...)`][Scope::race] over the futures.
+/// The first to complete wins, and the rest are aborted and dropped.
+///
+/// ```rust
+/// # futures::executor::block_on(async {
+/// let result = moro::infallible_scope!(|scope| {
+///     moro::race!(scope, futures::future::pending(), async { 22 }).await
+/// })
+/// .await;
+/// assert_eq!(result, 22);
+/// # });
+/// ```
+#[macro_export]
+macro_rules! race {
+    ($scope:expr, $($future:expr),+ $(,)?) => {
+        $scope.race([$(
+            ::std::boxed::Box::pin($future)
+                as ::std::pin::Pin<::std::boxed::Box<dyn ::std::future::Future<Output = _> + '_>>
+        ),+])
+    };
+}
+
 use futures::future::LocalBoxFuture;
 
+pub use self::cancel_scope::CancelScope;
+pub use self::cancellable_scope::CancellableScope;
+pub use self::finalizer::{FinalizerError, FinalizerOutput};
+pub use self::job_builder::JobBuilder;
+pub use self::multi_error::{ErrorMode, MultiError};
 pub use self::scope::Scope;
 pub use self::scope_body::ScopeBody;
-pub use self::spawned::Spawned;
+pub use self::spawned::{Aborted, Spawned};
+pub use self::supervisor::{Backoff, ChildFailure, Strategy, Supervisor, SupervisorError};
 
 /// Creates a new moro scope. Normally, you invoke this through `moro::async_scope!`.
+#[track_caller]
 pub fn scope_fn<'env, R, B>(body: B) -> ScopeBody<'env, R, LocalBoxFuture<'env, R>>
 where
-    R: Send + 'env,
-    for<'scope> B: FnOnce(&'scope Scope<'scope, 'env, R>) -> LocalBoxFuture<'scope, R>,
+    R: 'env,
+    for<'scope> B: FnOnce(&'scope CancellableScope<'scope, 'env, R>) -> LocalBoxFuture<'scope, R>,
 {
-    let scope = Scope::new();
+    let scope = Rc::new(CancellableScope::new());
 
-    // Unsafe: We are letting the body use the `Arc<Scope>` without reference
+    // Unsafe: We are letting the body use the `Rc<Scope>` without reference
     // counting. The reference is held by `Body` below. `Body` will not drop
-    // the `Arc` until the body_future is dropped, and the output `T` has to outlive
+    // the `Rc` until the body_future is dropped, and the output `T` has to outlive
     // `'env` so it can't reference `scope`, so this should be ok.
-    let scope_ref: *const Scope<'_, '_, R> = &*scope;
+    let scope_ref: *const CancellableScope<'_, '_, R> = &*scope;
     let body_future = body(unsafe { &*scope_ref });
 
     ScopeBody::new(body::Body::new(body_future, scope))
 }
 
 /// Creates a new moro scope.
+#[track_caller]
 pub fn scope<'env, R, B>(
     body: B,
-) -> ScopeBody<'env, R, <B as AsyncFnOnce<(&'env scope::Scope<'env, 'env, R>,)>>::CallOnceFuture>
+) -> ScopeBody<'env, R, <B as AsyncFnOnce<(&'env CancellableScope<'env, 'env, R>,)>>::CallOnceFuture>
 where
-    R: Send + 'env,
-    for<'scope> B: async FnOnce(&'scope Scope<'scope, 'env, R>) -> R,
+    R: 'env,
+    for<'scope> B: async FnOnce(&'scope CancellableScope<'scope, 'env, R>) -> R,
 {
-    let scope = Scope::new();
+    let scope = Rc::new(CancellableScope::new());
 
-    // Unsafe: We are letting the body use the `Arc<Scope>` without reference
+    // Unsafe: We are letting the body use the `Rc<Scope>` without reference
     // counting. The reference is held by `Body` below. `Body` will not drop
-    // the `Arc` until the body_future is dropped, and the output `T` has to outlive
+    // the `Rc` until the body_future is dropped, and the output `T` has to outlive
     // `'env` so it can't reference `scope`, so this should be ok.
-    let scope_ref: *const Scope<'_, '_, R> = &*scope;
+    let scope_ref: *const CancellableScope<'_, '_, R> = &*scope;
+    let body_future = body(unsafe { &*scope_ref });
+
+    ScopeBody::new(body::Body::new(body_future, scope))
+}
+
+/// Creates a new infallible moro scope. Normally, you invoke this through `moro::infallible_scope!`.
+#[track_caller]
+pub fn infallible_scope_fn<'env, R, B>(body: B) -> ScopeBody<'env, R, LocalBoxFuture<'env, R>>
+where
+    R: 'env,
+    for<'scope> B: FnOnce(&'scope Scope<'scope, 'env>) -> LocalBoxFuture<'scope, R>,
+{
+    // The scope is never terminated: the body only sees the `Scope`, which has
+    // no `terminate`.
+    let scope = Rc::new(CancellableScope::new());
+
+    // Unsafe: see `scope_fn`.
+    let scope_ref: *const CancellableScope<'_, '_, R> = &*scope;
+    let body_future = body(unsafe { &*scope_ref });
+
+    ScopeBody::new(body::Body::new(body_future, scope))
+}
+
+/// Creates a new infallible moro scope.
+#[track_caller]
+pub fn infallible_scope<'env, R, B>(
+    body: B,
+) -> ScopeBody<'env, R, <B as AsyncFnOnce<(&'env Scope<'env, 'env>,)>>::CallOnceFuture>
+where
+    R: 'env,
+    for<'scope> B: async FnOnce(&'scope Scope<'scope, 'env>) -> R,
+{
+    // The scope is never terminated: the body only sees the `Scope`, which has
+    // no `terminate`.
+    let scope = Rc::new(CancellableScope::new());
+
+    // Unsafe: see `scope_fn`.
+    let scope_ref: *const CancellableScope<'_, '_, R> = &*scope;
     let body_future = body(unsafe { &*scope_ref });
 
     ScopeBody::new(body::Body::new(body_future, scope))
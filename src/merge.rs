@@ -0,0 +1,172 @@
+use std::{
+    cell::RefCell,
+    ops::ControlFlow,
+    rc::Rc,
+    task::{Poll, Waker},
+};
+
+use futures::future::poll_fn;
+
+use crate::{AsyncIterator, Scope, Spawned, Stream};
+
+/// The item each producer has handed over, if the consumer has not taken it yet.
+struct Slot<T> {
+    item: Option<T>,
+
+    /// The producer, if it is waiting for the slot to be emptied.
+    waker: Option<Waker>,
+}
+
+struct Shared<T> {
+    slots: RefCell<Vec<Slot<T>>>,
+
+    /// The consumer, if it is waiting for an item.
+    consumer: RefCell<Option<Waker>>,
+}
+
+impl<T> Shared<T> {
+    /// Hands `item` over to the consumer, waiting until the producer's previous item has
+    /// been taken.
+    async fn push(&self, index: usize, item: T) {
+        let mut item = Some(item);
+        poll_fn(|cx| {
+            {
+                let mut slots = self.slots.borrow_mut();
+                let slot = &mut slots[index];
+                if slot.item.is_some() {
+                    slot.waker = Some(cx.waker().clone());
+                    return Poll::Pending;
+                }
+                slot.item = item.take();
+            }
+            if let Some(consumer) = self.consumer.take() {
+                consumer.wake();
+            }
+            Poll::Ready(())
+        })
+        .await
+    }
+}
+
+/// Runs each of several streams as a producer job and yields their items as they come.
+/// Each producer has room for one item, so a fast producer waits for the consumer instead
+/// of crowding out the others, and the consumer takes from the producers in turn.
+pub(crate) struct Merge<'scope, T> {
+    shared: Rc<Shared<T>>,
+
+    /// The producer jobs, `None` once they have finished.
+    producers: Vec<Option<Spawned<'scope, ()>>>,
+
+    /// Where the consumer looks for its next item first.
+    next: usize,
+}
+
+impl<'scope, T: 'scope> Merge<'scope, T> {
+    pub(crate) fn new() -> Self {
+        Self {
+            shared: Rc::new(Shared {
+                slots: RefCell::new(vec![]),
+                consumer: RefCell::new(None),
+            }),
+            producers: vec![],
+            next: 0,
+        }
+    }
+
+    /// Spawns a producer job for `stream`.
+    pub(crate) fn add<S>(&mut self, scope: &'scope Scope<'scope, '_>, mut stream: S)
+    where
+        S: Stream<Item = T> + 'scope,
+    {
+        let index = self.producers.len();
+        self.shared.slots.borrow_mut().push(Slot {
+            item: None,
+            waker: None,
+        });
+        let shared = self.shared.clone();
+        self.producers.push(Some(scope.spawn(async move {
+            stream
+                .for_each(async |item| shared.push(index, item).await)
+                .await
+        })));
+    }
+}
+
+impl<T> Merge<'_, T> {
+    /// Takes the next waiting item, starting from the producer after the one that
+    /// produced the last item.
+    fn take_item(&mut self) -> Option<T> {
+        let count = self.producers.len();
+        for offset in 0..count {
+            let index = (self.next + offset) % count;
+            let (item, waker) = {
+                let mut slots = self.shared.slots.borrow_mut();
+                let slot = &mut slots[index];
+                (slot.item.take(), slot.waker.take())
+            };
+            if let Some(waker) = waker {
+                waker.wake();
+            }
+            if item.is_some() {
+                self.next = index + 1;
+                return item;
+            }
+        }
+        None
+    }
+}
+
+impl<T> AsyncIterator for Merge<'_, T> {
+    type Item = T;
+
+    async fn next(&mut self) -> Option<Self::Item> {
+        poll_fn(|cx| {
+            *self.shared.consumer.borrow_mut() = Some(cx.waker().clone());
+            loop {
+                if let Some(item) = self.take_item() {
+                    return Poll::Ready(Some(item));
+                }
+                if self.producers.iter().all(Option::is_none) {
+                    return Poll::Ready(None);
+                }
+
+                // A producer that finished (or was aborted) may have left an item behind,
+                // so look at the slots again before deciding.
+                let mut finished = false;
+                for producer in &mut self.producers {
+                    if let Some(handle) = producer {
+                        if handle.poll_result(cx).is_ready() {
+                            *producer = None;
+                            finished = true;
+                        }
+                    }
+                }
+                if !finished {
+                    return Poll::Pending;
+                }
+            }
+        })
+        .await
+    }
+}
+
+impl<T> Stream for Merge<'_, T> {
+    async fn try_fold<R>(
+        &mut self,
+        mut acc: R,
+        mut op: impl async FnMut(R, Self::Item) -> ControlFlow<R, R>,
+    ) -> ControlFlow<R, R> {
+        while let Some(item) = self.next().await {
+            acc = op(acc, item).await?;
+        }
+        ControlFlow::Continue(acc)
+    }
+}
+
+impl<T> Drop for Merge<'_, T> {
+    fn drop(&mut self) {
+        for handle in self.producers.iter().flatten() {
+            handle.abort();
+        }
+    }
+}
@@ -0,0 +1,80 @@
+use std::fmt;
+
+/// How a scope whose result is a [`Result`] handles errors; see
+/// [`ScopeBody::with_error_mode`][crate::ScopeBody::with_error_mode].
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum ErrorMode {
+    /// The first error terminates the scope, as [`terminate`][crate::CancellableScope::terminate]
+    /// normally does.
+    #[default]
+    FailFast,
+
+    /// Terminating the scope with an error records the error and stops only the job
+    /// (or scope body) that reported it. The rest of the scope runs to completion, and
+    /// its result has every error that was reported.
+    CollectAll,
+}
+
+/// The errors reported within a scope, in the order they were reported. Returned by
+/// [`ScopeBody::with_error_mode`][crate::ScopeBody::with_error_mode].
+///
+/// There is always at least one error.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MultiError<E> {
+    errors: Vec<E>,
+}
+
+impl<E> MultiError<E> {
+    pub(crate) fn new(errors: Vec<E>) -> Self {
+        assert!(!errors.is_empty());
+        Self { errors }
+    }
+
+    /// The errors, in the order they were reported.
+    pub fn errors(&self) -> &[E] {
+        &self.errors
+    }
+
+    /// Consumes `self`, returning the errors in the order they were reported.
+    pub fn into_errors(self) -> Vec<E> {
+        self.errors
+    }
+}
+
+impl<E> IntoIterator for MultiError<E> {
+    type Item = E;
+    type IntoIter = std::vec::IntoIter<E>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.errors.into_iter()
+    }
+}
+
+impl<'a, E> IntoIterator for &'a MultiError<E> {
+    type Item = &'a E;
+    type IntoIter = std::slice::Iter<'a, E>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.errors.iter()
+    }
+}
+
+impl<E: fmt::Display> fmt::Display for MultiError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.errors[..] {
+            [error] => write!(f, "{error}"),
+            [first, ..] => write!(
+                f,
+                "{} errors occurred; the first: {first}",
+                self.errors.len()
+            ),
+            [] => unreachable!(),
+        }
+    }
+}
+
+impl<E: std::error::Error + 'static> std::error::Error for MultiError<E> {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.errors[0])
+    }
+}
@@ -0,0 +1,59 @@
+use std::{
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use futures::Future;
+
+use crate::Spawned;
+
+/// The future returned by [`Scope::race`][crate::Scope::race]. Dropping it aborts the
+/// racing jobs.
+pub(crate) struct Race<'scope, T> {
+    jobs: Vec<Spawned<'scope, T>>,
+}
+
+impl<'scope, T> Race<'scope, T> {
+    pub(crate) fn new(jobs: Vec<Spawned<'scope, T>>) -> Self {
+        assert!(!jobs.is_empty(), "`race` needs at least one future");
+        Self { jobs }
+    }
+
+    fn abort_all(&mut self) {
+        for job in self.jobs.drain(..) {
+            job.abort();
+        }
+    }
+}
+
+impl<T> Future for Race<'_, T> {
+    type Output = T;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<T> {
+        let mut index = 0;
+        while index < self.jobs.len() {
+            match self.jobs[index].poll_result(cx) {
+                Poll::Ready(Ok(v)) => {
+                    self.abort_all();
+                    return Poll::Ready(v);
+                }
+                // A job that was aborted some other way (e.g. by a cancel scope) is
+                // out of the race.
+                Poll::Ready(Err(_)) => {
+                    self.jobs.remove(index);
+                }
+                Poll::Pending => index += 1,
+            }
+        }
+        if self.jobs.is_empty() {
+            panic!("every job in the race was aborted");
+        }
+        Poll::Pending
+    }
+}
+
+impl<T> Drop for Race<'_, T> {
+    fn drop(&mut self) {
+        self.abort_all();
+    }
+}
@@ -0,0 +1,167 @@
+use std::{
+    cell::{Cell, RefCell},
+    collections::BTreeMap,
+    panic::Location,
+    rc::{Rc, Weak},
+    time::Instant,
+};
+
+/// Identifies a job (or scope body) among all of those created on this thread, in any
+/// scope. Keys increase in creation order.
+pub(crate) type JobKey = u64;
+
+/// Keeps track of the jobs on this thread. Unlike the rest of a scope's state, this is
+/// shared between scopes, so that it also covers jobs of nested scopes.
+#[derive(Default)]
+struct Registry {
+    next_key: Cell<JobKey>,
+
+    /// The job (or scope body) being polled, if any.
+    current: Cell<Option<JobKey>>,
+
+    /// The records of the jobs (and scope bodies) that have not been dropped.
+    records: RefCell<BTreeMap<JobKey, Weak<JobRecord>>>,
+}
+
+thread_local! {
+    static REGISTRY: Registry = Registry::default();
+}
+
+/// The job (or scope body) being polled on this thread, if any.
+pub(crate) fn current_job() -> Option<JobKey> {
+    REGISTRY.with(|registry| registry.current.get())
+}
+
+/// What a job (or scope body) is recorded as.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(crate) enum Kind {
+    Body,
+
+    /// A job, with its position in its scope's spawn order.
+    Job(usize),
+
+    /// A job that runs finalizers, with its position in its scope's spawn order.
+    Finalizers(usize),
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(crate) enum Status {
+    /// Waiting for the scope's concurrency limit.
+    Enqueued,
+    Pending,
+    Polling,
+    Done,
+    Panicked,
+    Aborted,
+
+    /// Stopped after recording an error with the scope.
+    Failed,
+}
+
+impl Status {
+    pub(crate) fn as_str(self) -> &'static str {
+        match self {
+            Status::Enqueued => "enqueued",
+            Status::Pending => "pending",
+            Status::Polling => "polling",
+            Status::Done => "done",
+            Status::Panicked => "panicked",
+            Status::Aborted => "aborted",
+            Status::Failed => "failed",
+        }
+    }
+}
+
+/// What is known about a job (or scope body) for [`Scope::dump`][crate::Scope::dump].
+///
+/// Records form a tree: each one is a child of the job (or scope body) that was being
+/// polled when it was created, which may be in an enclosing scope. A record keeps its
+/// parent alive, so that the tree stays connected after a parent job has finished.
+pub(crate) struct JobRecord {
+    pub(crate) key: JobKey,
+    pub(crate) kind: Kind,
+
+    /// Where the job was spawned (or the scope created).
+    pub(crate) location: Option<&'static Location<'static>>,
+    pub(crate) name: Option<String>,
+    pub(crate) status: Cell<Status>,
+    pub(crate) polls: Cell<u64>,
+    pub(crate) last_poll: Cell<Option<Instant>>,
+
+    /// Only held to keep the parent alive.
+    _parent: Option<Rc<JobRecord>>,
+    pub(crate) children: RefCell<Vec<Weak<JobRecord>>>,
+}
+
+impl JobRecord {
+    /// Records a new job (or scope body), as a child of the one being polled.
+    pub(crate) fn new(
+        kind: Kind,
+        status: Status,
+        location: Option<&'static Location<'static>>,
+        name: Option<String>,
+    ) -> Rc<Self> {
+        REGISTRY.with(|registry| {
+            let key = registry.next_key.get();
+            registry.next_key.set(key + 1);
+
+            let mut records = registry.records.borrow_mut();
+            let parent = registry
+                .current
+                .get()
+                .and_then(|parent| records.get(&parent)?.upgrade());
+            let record = Rc::new(Self {
+                key,
+                kind,
+                location,
+                name,
+                status: Cell::new(status),
+                polls: Default::default(),
+                last_poll: Default::default(),
+                _parent: parent.clone(),
+                children: Default::default(),
+            });
+            if let Some(parent) = parent {
+                // Finished children are pruned only when the list is full, which keeps
+                // recording a job amortized O(1).
+                let mut children = parent.children.borrow_mut();
+                if children.len() == children.capacity() {
+                    children.retain(|child| child.strong_count() > 0);
+                }
+                children.push(Rc::downgrade(&record));
+            }
+            records.insert(key, Rc::downgrade(&record));
+            record
+        })
+    }
+}
+
+impl Drop for JobRecord {
+    fn drop(&mut self) {
+        // The registry may already be gone if the thread is exiting.
+        let _ = REGISTRY.try_with(|registry| registry.records.borrow_mut().remove(&self.key));
+    }
+}
+
+/// Marks a job (or scope body) as the one being polled, until dropped.
+pub(crate) struct Polling {
+    previous: Option<JobKey>,
+}
+
+impl Polling {
+    /// Also counts the poll. The caller updates the record's status once the poll is
+    /// over.
+    pub(crate) fn enter(record: &JobRecord) -> Self {
+        record.polls.set(record.polls.get() + 1);
+        record.last_poll.set(Some(Instant::now()));
+        record.status.set(Status::Polling);
+        let previous = REGISTRY.with(|registry| registry.current.replace(Some(record.key)));
+        Self { previous }
+    }
+}
+
+impl Drop for Polling {
+    fn drop(&mut self) {
+        REGISTRY.with(|registry| registry.current.set(self.previous));
+    }
+}
@@ -1,34 +1,37 @@
-use crate::Scope;
+use crate::CancellableScope;
 
 #[async_trait::async_trait(?Send)]
 pub trait UnwrapOrCancel: Sized {
     type Ok;
     type Err;
 
-    async fn unwrap_or_cancel<'scope, 'env, T>(
+    /// Unwraps an `Ok` value, or terminates `scope` with the error, converted with `From`
+    /// (just like the `?` operator).
+    async fn unwrap_or_cancel<'scope, 'env, T, E>(
         self,
-        scope: &'scope Scope<'scope, 'env, Result<T, Self::Err>>,
+        scope: &'scope CancellableScope<'scope, 'env, Result<T, E>>,
     ) -> Self::Ok
     where
-        T: Send,
+        E: From<Self::Err>,
         Self: 'env;
 }
 
 #[async_trait::async_trait(?Send)]
-impl<O, E> UnwrapOrCancel for Result<O, E> {
+impl<O, E1> UnwrapOrCancel for Result<O, E1> {
     type Ok = O;
-    type Err = E;
+    type Err = E1;
 
-    async fn unwrap_or_cancel<'scope, 'env, T>(
+    async fn unwrap_or_cancel<'scope, 'env, T, E>(
         self,
-        scope: &'scope Scope<'scope, 'env, Result<T, E>>,
+        scope: &'scope CancellableScope<'scope, 'env, Result<T, E>>,
     ) -> O
     where
+        E: From<E1>,
         Self: 'env,
     {
         match self {
             Ok(o) => o,
-            Err(e) => scope.terminate(Err(e)).await,
+            Err(e) => scope.terminate(Err(E::from(e))).await,
         }
     }
 }
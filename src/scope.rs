@@ -1,156 +1,1252 @@
 use std::{
+    any::Any,
+    cell::{Cell, OnceCell, RefCell},
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap},
     marker::PhantomData,
+    panic::{AssertUnwindSafe, Location},
     pin::Pin,
-    sync::{Arc, Mutex},
-    task::Poll,
+    rc::{Rc, Weak},
+    task::{Poll, Waker},
+    time::Instant,
 };
 
-use futures::{future::LocalBoxFuture, stream::FuturesUnordered, Future, Stream};
+use futures::{future::LocalBoxFuture, stream::FuturesUnordered, Future, FutureExt, Stream};
 
-use crate::Spawned;
+use crate::{
+    channel::{self, Channel, OneshotReceiver, OneshotSender, Receiver, Sender},
+    finalizer::{run_finalizers, Finalizer, FinalizerError, FinalizerOutput},
+    job::{ErasedJob, FinalizerErrorHandler, Finished, Job, JobCell, SharedState, Waiting},
+    job_builder::JobOptions,
+    race::Race,
+    registry::{self, JobRecord, Kind, Status},
+    time::Timer,
+    trace, Aborted, CancelScope, JobBuilder, ScopeDump, Spawned, Supervisor,
+};
 
 /// Represents a moro "async scope". See the [`async_scope`][crate::async_scope] macro for details.
-pub struct Scope<'scope, 'env: 'scope, R: 'env> {
+///
+/// A `Scope` does not know the type of the value the scope produces, so helpers that
+/// only spawn jobs can accept any `&Scope`. The scope body is given a
+/// [`CancellableScope`][crate::CancellableScope], which derefs to a `Scope` and adds
+/// [`terminate`](crate::CancellableScope::terminate).
+///
+/// # Examples
+///
+/// ```rust
+/// async fn sum<'scope>(scope: &'scope moro::Scope<'scope, '_>, v: &'scope [i32]) -> i32 {
+///     scope.spawn(async { v.iter().sum() }).output().await
+/// }
+///
+/// # futures::executor::block_on(async {
+/// let v = vec![1, 2, 3];
+/// let result = moro::async_scope!(|scope| sum(scope, &v).await).await;
+/// assert_eq!(result, 6);
+/// # });
+/// ```
+pub struct Scope<'scope, 'env: 'scope> {
     /// Stores the set of futures that have been spawned.
     ///
-    /// This is behind a mutex so that multiple concurrent actors can access it.
-    /// A `RwLock` seems better, but `FuturesUnordered is not `Sync` in the case.
-    /// But in fact it doesn't matter anyway, because all spawned futures execute
-    /// CONCURRENTLY and hence there will be no contention.
-    futures: Mutex<Pin<Box<FuturesUnordered<LocalBoxFuture<'scope, ()>>>>>,
-    enqueued: Mutex<Vec<LocalBoxFuture<'scope, ()>>>,
-    terminated: Mutex<Option<R>>,
+    /// All jobs execute CONCURRENTLY on the thread polling the scope, never in
+    /// parallel, so single-threaded cells suffice throughout (and make `Scope` `!Sync`).
+    /// `futures` is borrowed for as long as the jobs are being polled, so jobs
+    /// that spawn more jobs only touch `enqueued` and `enqueued_shielded`.
+    futures: RefCell<Pin<Box<FuturesUnordered<Job<'scope>>>>>,
+
+    /// Jobs spawned with [`Scope::shield`]. These keep running after the scope is terminated.
+    shielded: RefCell<Pin<Box<FuturesUnordered<Job<'scope>>>>>,
+
+    /// Unshielded jobs that have not started yet: they wait here for room under the
+    /// concurrency limit. The heap pops the highest priority first, and otherwise the
+    /// earliest spawned.
+    enqueued: RefCell<BinaryHeap<Job<'scope>>>,
+
+    /// Shielded jobs that have not started yet. The concurrency limit does not apply to
+    /// them, so they start at the next poll.
+    enqueued_shielded: RefCell<Vec<Job<'scope>>>,
+
+    /// Whether the scope is terminated (the terminal value itself is held by the
+    /// [`CancellableScope`][crate::CancellableScope]), and other state that jobs need.
+    shared: SharedState<'scope>,
+
+    /// Creates the future that bounds how long shielded jobs may keep running
+    /// once the scope has been terminated.
+    grace_period: Cell<Option<GracePeriod<'scope>>>,
+
+    /// The grace period, once it has started.
+    grace: RefCell<Option<LocalBoxFuture<'scope, ()>>>,
+
+    /// Measures deadlines; see [`Scope::timer`].
+    timer: RefCell<Option<Rc<dyn Timer>>>,
+
+    /// Payload of the first job that panicked, if any. It is resumed by the scope body
+    /// once the remaining jobs have been cancelled.
+    panicked: RefCell<Option<Box<dyn Any + Send>>>,
+
+    /// Maximum number of (unshielded) jobs that may run at once; further jobs wait
+    /// in `enqueued`. See [`ScopeBody::with_max_concurrency`][crate::ScopeBody::with_max_concurrency].
+    max_concurrency: Cell<Option<usize>>,
+
+    /// Number of unshielded jobs that have been spawned but not completed,
+    /// whether they are running or still enqueued.
+    live_jobs: Cell<usize>,
+
+    /// Tasks waiting in [`Scope::spawn_limited`] for `live_jobs` to drop below
+    /// `max_concurrency`.
+    capacity_waiters: RefCell<Vec<Waker>>,
+
+    /// Finalizers registered with [`Scope::defer`] and [`Scope::on_cancel`].
+    deferred: RefCell<Vec<Finalizer<'scope>>>,
+
+    /// The shielded job that runs `deferred` once the scope ends. It is created along
+    /// with the first finalizer, and started by [`Scope::start_deferred`].
+    deferred_job: RefCell<Option<Job<'scope>>>,
+
+    /// Set once the jobs that have finalizers have been cancelled on termination.
+    finalized_cancelled_jobs: Cell<bool>,
+
+    /// Channels created with [`Scope::channel`] and [`Scope::oneshot`], which are closed
+    /// when the scope is terminated.
+    channels: RefCell<Vec<Weak<dyn Channel + 'scope>>>,
+
+    /// The scope body's record, at the root of the scope's [dump](Scope::dump), if the
+    /// scope records a task tree.
+    record: OnceCell<Rc<JobRecord>>,
+
+    /// Where the scope was created.
+    location: &'static Location<'static>,
+
+    /// Entered while the body is polled, with the `tracing` feature.
+    span: trace::Span,
     phantom: PhantomData<&'scope &'env ()>,
 }
 
-impl<'scope, 'env, R> Scope<'scope, 'env, R> {
+/// Creates the future that bounds the grace period of shielded jobs.
+pub(crate) type GracePeriod<'scope> = Box<dyn FnOnce() -> LocalBoxFuture<'scope, ()> + 'scope>;
+
+impl<'scope, 'env> Scope<'scope, 'env> {
     /// Create a scope.
-    pub(crate) fn new() -> Arc<Self> {
-        Arc::new(Self {
-            futures: Mutex::new(Box::pin(FuturesUnordered::new())),
+    #[track_caller]
+    pub(crate) fn new() -> Self {
+        let scope = Self {
+            futures: RefCell::new(Box::pin(FuturesUnordered::new())),
+            shielded: RefCell::new(Box::pin(FuturesUnordered::new())),
             enqueued: Default::default(),
-            terminated: Default::default(),
+            enqueued_shielded: Default::default(),
+            shared: Default::default(),
+            grace_period: Default::default(),
+            grace: Default::default(),
+            timer: Default::default(),
+            panicked: Default::default(),
+            max_concurrency: Default::default(),
+            live_jobs: Default::default(),
+            capacity_waiters: Default::default(),
+            deferred: Default::default(),
+            deferred_job: Default::default(),
+            finalized_cancelled_jobs: Default::default(),
+            channels: Default::default(),
+            record: Default::default(),
+            location: Location::caller(),
+            span: trace::Span::new(Kind::Body, Some(Location::caller()), None),
             phantom: Default::default(),
-        })
+        };
+
+        // A scope created by a job that is recorded in a task tree is part of that tree.
+        if registry::current_job().is_some() {
+            scope.set_record_tasks();
+        }
+        scope
     }
 
     /// Polls the jobs that were spawned thus far. Returns:
     ///
     /// * `Pending` if there are jobs that cannot complete
-    /// * `Ready(Ok(()))` if all jobs are completed
-    /// * `Ready(Err(c))` if the scope has been canceled
+    /// * `Ready(())` if all jobs are completed, if a job panicked
+    ///   (check [`Self::take_panic`]), or if the scope has been canceled and its
+    ///   shielded jobs have completed (or the grace period ran out)
     ///
-    /// Should not be invoked again once `Ready(Err(c))` is returned.
+    /// Once the scope has been canceled and `Ready(())` is returned, it should only
+    /// be invoked again to run finalizers (see [`Self::start_deferred`]); they get
+    /// no more time than the grace period allows.
     ///
-    /// It is ok to invoke it again after `Ready(Ok(()))` has been returned;
+    /// It is ok to invoke it again otherwise;
     /// if any new jobs have been spawned, they will execute.
-    pub(crate) fn poll_jobs(&self, cx: &mut std::task::Context<'_>) -> Poll<Option<R>> {
-        let mut futures = self.futures.lock().unwrap();
+    pub(crate) fn poll_jobs(&self, cx: &mut std::task::Context<'_>) -> Poll<()> {
+        let mut futures = self.futures.borrow_mut();
+        let mut shielded = self.shielded.borrow_mut();
         'outer: loop {
-            // once we are terminated, we do no more work.
-            if let Some(r) = self.terminated.lock().unwrap().take() {
-                return Poll::Ready(Some(r));
+            // once a job has panicked, we do no more work.
+            if self.panicked.borrow().is_some() {
+                return Poll::Ready(());
             }
 
-            futures.extend(self.enqueued.lock().unwrap().drain(..));
+            let terminated = self.is_terminated();
+            if terminated && !self.finalized_cancelled_jobs.replace(true) {
+                self.finalize_cancelled_jobs(&futures);
+            }
+            self.admit(&mut futures, &mut shielded, terminated);
 
-            while let Some(()) = ready!(futures.as_mut().poll_next(cx)) {
-                // once we are terminated, we do no more work.
-                if self.terminated.lock().unwrap().is_some() {
-                    continue 'outer;
+            // once we are terminated, only shielded jobs do any more work.
+            let mut pending = false;
+            if !terminated {
+                match self.poll_set(futures.as_mut(), terminated, cx) {
+                    Poll::Ready(true) => {}
+                    Poll::Ready(false) => continue 'outer,
+                    Poll::Pending => pending = true,
                 }
             }
+            match self.poll_set(shielded.as_mut(), terminated, cx) {
+                Poll::Ready(true) => {}
+                Poll::Ready(false) => continue 'outer,
+                Poll::Pending => pending = true,
+            }
 
-            if self.enqueued.lock().unwrap().is_empty() {
-                return Poll::Ready(None);
+            // A job may have terminated the scope without completing.
+            if self.is_terminated() != terminated || self.can_admit(futures.len(), terminated) {
+                continue 'outer;
             }
+
+            if terminated {
+                // Give the shielded jobs a chance to finish, unless the grace period
+                // runs out first.
+                if pending && self.poll_grace(cx).is_pending() {
+                    return Poll::Pending;
+                }
+                return Poll::Ready(());
+            }
+
+            return if pending {
+                Poll::Pending
+            } else {
+                Poll::Ready(())
+            };
+        }
+    }
+
+    /// Aborts the unshielded jobs that have finalizers, so that the finalizers run
+    /// now that the scope has been terminated. The other jobs are left to be dropped
+    /// along with the scope.
+    fn finalize_cancelled_jobs(&self, futures: &FuturesUnordered<Job<'scope>>) {
+        let enqueued = self.enqueued.borrow();
+        let mut jobs: Vec<(usize, Rc<dyn ErasedJob + 'scope>)> = futures
+            .iter()
+            .chain(enqueued.iter())
+            .filter(|job| job.cell.has_finalizers())
+            .map(|job| (job.id, job.cell.clone()))
+            .collect();
+        drop(enqueued);
+        jobs.sort_by_key(|&(id, _)| Reverse(id));
+        for (_, job) in jobs {
+            job.abort();
         }
     }
 
+    /// Moves enqueued jobs into `futures` or `shielded` so that they start running.
+    /// Unshielded jobs beyond the concurrency limit stay enqueued, unless the scope has
+    /// been terminated (in which case they will never run anyway).
+    fn admit(
+        &self,
+        futures: &mut FuturesUnordered<Job<'scope>>,
+        shielded: &mut FuturesUnordered<Job<'scope>>,
+        terminated: bool,
+    ) {
+        for job in self.shared.finalizers.take() {
+            shielded.push(job);
+        }
+
+        for job in self.enqueued_shielded.take() {
+            shielded.push(job);
+        }
+
+        let mut enqueued = self.enqueued.borrow_mut();
+        let admitted = match self.free_slots(futures.len(), terminated) {
+            Some(free) => free.min(enqueued.len()),
+            None => enqueued.len(),
+        };
+        for job in std::iter::from_fn(|| enqueued.pop()).take(admitted) {
+            job.cell.admit();
+            futures.push(job);
+        }
+    }
+
+    /// True if [`admit`](Self::admit) would start any enqueued jobs.
+    fn can_admit(&self, running: usize, terminated: bool) -> bool {
+        let has_room = self
+            .free_slots(running, terminated)
+            .is_none_or(|free| free > 0);
+        (has_room && !self.enqueued.borrow().is_empty())
+            || !self.enqueued_shielded.borrow().is_empty()
+            || !self.shared.finalizers.borrow().is_empty()
+    }
+
+    /// How many more unshielded jobs may start while `running` are running, or `None`
+    /// if there is no limit (which is also the case once the scope has been terminated).
+    fn free_slots(&self, running: usize, terminated: bool) -> Option<usize> {
+        if terminated {
+            return None;
+        }
+        let max_concurrency = self.max_concurrency.get()?;
+        Some(max_concurrency.saturating_sub(running))
+    }
+
+    /// Limits the number of unshielded jobs that may run at once.
+    pub(crate) fn set_max_concurrency(&self, max_concurrency: usize) {
+        assert!(max_concurrency > 0, "max_concurrency must be at least 1");
+        self.max_concurrency.set(Some(max_concurrency));
+    }
+
+    /// True if an unshielded job can be spawned without exceeding the concurrency limit.
+    fn has_capacity(&self) -> bool {
+        let max_concurrency = self.max_concurrency.get();
+        max_concurrency.is_none_or(|max| self.live_jobs.get() < max)
+    }
+
+    /// Records that an unshielded job has completed, waking anyone waiting in
+    /// [`spawn_limited`](Self::spawn_limited).
+    fn job_completed(&self) {
+        self.live_jobs.set(self.live_jobs.get() - 1);
+        for waker in self.capacity_waiters.take() {
+            waker.wake();
+        }
+    }
+
+    /// Polls `jobs` until they have all finished (`Ready(true)`) or none of them
+    /// can make progress (`Pending`). Returns `Ready(false)` early if a job panics or if
+    /// the scope is terminated while `terminated` is false.
+    fn poll_set(
+        &self,
+        mut jobs: Pin<&mut FuturesUnordered<Job<'scope>>>,
+        terminated: bool,
+        cx: &mut std::task::Context<'_>,
+    ) -> Poll<bool> {
+        while let Some(Finished { shielded, panic }) = ready!(jobs.as_mut().poll_next(cx)) {
+            if let Some(payload) = panic {
+                self.panicked.borrow_mut().get_or_insert(payload);
+            }
+            if !shielded {
+                self.job_completed();
+            }
+            if self.panicked.borrow().is_some() || self.is_terminated() != terminated {
+                return Poll::Ready(false);
+            }
+        }
+        Poll::Ready(true)
+    }
+
+    /// Polls the grace period for shielded jobs, starting it if needed.
+    /// Without a grace period, shielded jobs may run for as long as they need.
+    fn poll_grace(&self, cx: &mut std::task::Context<'_>) -> Poll<()> {
+        let mut grace = self.grace.borrow_mut();
+        if grace.is_none() {
+            match self.grace_period.take() {
+                Some(grace_period) => *grace = Some(grace_period()),
+                None => return Poll::Pending,
+            }
+        }
+        grace.as_mut().unwrap().as_mut().poll(cx)
+    }
+
+    /// Bounds how long shielded jobs may keep running once the scope is terminated.
+    /// `grace_period` is invoked at termination, and when its future completes any
+    /// shielded jobs that are still running are dropped.
+    pub(crate) fn set_grace_period(&self, grace_period: GracePeriod<'scope>) {
+        self.grace_period.set(Some(grace_period));
+    }
+
+    pub(crate) fn set_timer(&self, timer: Rc<dyn Timer>) {
+        *self.timer.borrow_mut() = Some(timer);
+    }
+
+    /// The timer used for deadlines in this scope: the one given to
+    /// [`ScopeBody::with_timer`][crate::ScopeBody::with_timer], or else
+    /// [`TokioTimer`][crate::time::TokioTimer] if the `tokio` feature is enabled.
+    ///
+    /// # Panics
+    ///
+    /// If no timer was set and the `tokio` feature is disabled.
+    pub(crate) fn timer(&self) -> Rc<dyn Timer> {
+        self.try_timer()
+            .expect("no timer set for this scope; use `ScopeBody::with_timer`")
+    }
+
+    /// Like [`timer`](Self::timer), but `None` instead of panicking.
+    fn try_timer(&self) -> Option<Rc<dyn Timer>> {
+        let timer = self.timer.borrow().clone();
+        #[cfg(feature = "tokio")]
+        let timer = timer.or_else(|| Some(Rc::new(crate::time::TokioTimer) as Rc<dyn Timer>));
+        timer
+    }
+
+    /// The current time according to the scope's timer, or the system clock if it has
+    /// none.
+    pub(crate) fn now(&self) -> Instant {
+        match self.try_timer() {
+            Some(timer) => timer.now(),
+            None => Instant::now(),
+        }
+    }
+
+    /// True if [`terminate`](crate::CancellableScope::terminate) has been invoked.
+    pub(crate) fn is_terminated(&self) -> bool {
+        self.shared.terminated.get()
+    }
+
+    /// Marks the scope as terminated: from now on, only shielded jobs do any work.
+    pub(crate) fn set_terminated(&self) {
+        if !self.shared.terminated.replace(true) {
+            self.span.terminated();
+        }
+        for channel in self.channels.take() {
+            if let Some(channel) = channel.upgrade() {
+                channel.close();
+            }
+        }
+    }
+
+    /// The scope body's record, if the scope records a task tree.
+    pub(crate) fn record(&self) -> Option<&JobRecord> {
+        self.record.get().map(Rc::as_ref)
+    }
+
+    /// Records the scope body and the jobs spawned from now on in the task tree. See
+    /// [`ScopeBody::with_task_tree`][crate::ScopeBody::with_task_tree].
+    pub(crate) fn set_record_tasks(&self) {
+        self.shared.record_tasks.set(true);
+        self.record
+            .get_or_init(|| JobRecord::new(Kind::Body, Status::Pending, Some(self.location), None));
+    }
+
+    /// The scope body's span.
+    pub(crate) fn span(&self) -> &trace::Span {
+        &self.span
+    }
+
+    pub(crate) fn set_detect_deadlocks(&self) {
+        self.shared.detect_deadlocks.set(true);
+    }
+
+    pub(crate) fn detects_deadlocks(&self) -> bool {
+        self.shared.detect_deadlocks.get()
+    }
+
+    /// Checks whether the scope has stalled: neither the body (`body`, unless it has
+    /// finished) nor any job has been woken, and the only clones of their wakers are held
+    /// by the handles of other jobs that are stalled too, or by the scope's channels,
+    /// which only stalled jobs could send on or close. Invoked whenever the scope is
+    /// pending.
+    ///
+    /// A stalled scope fails the receive of one of the jobs (or the body) that waits on
+    /// a channel with [`RecvError::Stalled`][crate::RecvError::Stalled]. Otherwise, in
+    /// [deadlock detection](crate::ScopeBody::with_deadlock_detection) mode, it panics.
+    pub(crate) fn check_stalled(&self, body: Option<Waiting>) {
+        if self.is_terminated() {
+            return;
+        }
+
+        // Unless the scope detects deadlocks, a stall only matters if a receiver waits.
+        let channels: Vec<_> = self
+            .channels
+            .borrow()
+            .iter()
+            .filter_map(Weak::upgrade)
+            .collect();
+        let waiters: Vec<_> = channels.iter().map(|channel| channel.waiting()).collect();
+        if !self.detects_deadlocks() && waiters.iter().all(|w| w.receiver.is_none()) {
+            return;
+        }
+
+        let futures = self.futures.borrow();
+        let shielded = self.shielded.borrow();
+        let enqueued = self.enqueued.borrow();
+        let enqueued_shielded = self.enqueued_shielded.borrow();
+        let mut nodes: Vec<(Option<&Job<'scope>>, Waiting)> = body
+            .map(|body| (None, body))
+            .into_iter()
+            .chain(
+                futures
+                    .iter()
+                    .chain(shielded.iter())
+                    .chain(enqueued.iter())
+                    .chain(enqueued_shielded.iter())
+                    .filter_map(|job| Some((Some(job), job.cell.waiting()?))),
+            )
+            .collect();
+
+        // The clones of each waker that are held by the handles of the pending jobs and
+        // by the channels, for receiving and for sending.
+        let mut held: HashMap<*const (), usize> = HashMap::new();
+        let handles = nodes.iter().filter_map(|(_, node)| node.waiter);
+        let receivers = waiters.iter().filter_map(|w| w.receiver);
+        let senders = waiters.iter().flat_map(|w| w.senders.iter().copied());
+        for waker in handles.chain(receivers).chain(senders) {
+            *held.entry(waker).or_default() += 1;
+        }
+        let stalled = nodes.iter().all(|(_, node)| {
+            !node.woken && node.wakers == held.get(&node.waker).copied().unwrap_or(0)
+        });
+        if nodes.is_empty() || !stalled {
+            return;
+        }
+
+        // Fail one receive at a time: once its job has handled the error, it may have
+        // dropped senders that other receivers wait on.
+        let receiver = channels.iter().zip(&waiters).find(|(_, w)| {
+            w.receiver
+                .is_some_and(|waker| nodes.iter().any(|(_, node)| node.waker == waker))
+        });
+        if let Some((channel, _)) = receiver {
+            channel.fail_receiver();
+            return;
+        }
+        if !self.detects_deadlocks() {
+            return;
+        }
+
+        // Describe the stall from the body, or else from the first job spawned.
+        nodes.sort_by_key(|(job, _)| job.map(|job| job.id));
+        let label = |i: usize| match nodes[i].0 {
+            Some(job) => job_label(job),
+            None => "the scope body".to_string(),
+        };
+
+        // The nodes that `node` waits on, through their handles.
+        let nodes = &nodes;
+        let waits_on = |node: &Waiting| {
+            let waker = node.waker;
+            (0..nodes.len()).filter(move |&i| nodes[i].1.waiter == Some(waker))
+        };
+
+        // Follow the waits-on edges from the first node until they loop back or end.
+        let mut description = label(0);
+        let mut path = vec![0];
+        let mut subject = "";
+        loop {
+            let last = &nodes[*path.last().unwrap()].1;
+            let Some(next) = waits_on(last).next() else {
+                description += subject;
+                let sends = waiters.iter().any(|w| w.senders.contains(&last.waker));
+                description += if sends {
+                    " waits to send on a full channel that no job will receive from"
+                } else {
+                    " is pending with no waker"
+                };
+                break;
+            };
+            description += &format!("{subject} waits on {}", label(next));
+            if path.contains(&next) {
+                break;
+            }
+            path.push(next);
+            subject = ", which";
+        }
+        panic!("deadlock in moro scope: {description}");
+    }
+
+    /// Stops the job (or scope body) that is being polled, without terminating the scope.
+    pub(crate) fn stop_current(&self) {
+        self.shared.stop_current.set(true);
+    }
+
+    /// True (once) if [`stop_current`](Self::stop_current) was invoked while polling
+    /// the scope body.
+    pub(crate) fn take_stop_current(&self) -> bool {
+        self.shared.stop_current.take()
+    }
+
+    /// Takes the payload of the job that panicked, if any.
+    pub(crate) fn take_panic(&self) -> Option<Box<dyn Any + Send>> {
+        self.panicked.take()
+    }
+
     /// Clear out all pending jobs. This is used when dropping the
     /// scope body to ensure that any possible references to `Scope`
     /// are removed before we drop it.
     ///
+    /// Jobs are dropped one at a time, most recently spawned first.
+    ///
     /// # Unsafe contract
     ///
     /// Once this returns, there are no more pending tasks.
     pub(crate) fn clear(&self) {
-        self.futures.lock().unwrap().clear();
-        self.enqueued.lock().unwrap().clear();
+        let futures = std::mem::take(Pin::get_mut(self.futures.borrow_mut().as_mut()));
+        let shielded = std::mem::take(Pin::get_mut(self.shielded.borrow_mut().as_mut()));
+        let mut jobs: Vec<Job<'scope>> = futures.into_iter().chain(shielded).collect();
+        jobs.extend(self.enqueued.take());
+        jobs.append(&mut self.enqueued_shielded.borrow_mut());
+        jobs.append(&mut self.shared.finalizers.borrow_mut());
+        jobs.extend(self.deferred_job.take());
+        jobs.sort_by_key(|job| Reverse(job.id));
+        for job in jobs {
+            drop(job);
+        }
+        self.deferred.take();
+    }
+
+    /// Starts the finalizers registered with [`Scope::defer`] and [`Scope::on_cancel`],
+    /// now that the scope has ended. Returns true if there are finalizers to run, in
+    /// which case the caller should poll the jobs again.
+    pub(crate) fn start_deferred(&self) -> bool {
+        match self.deferred_job.take() {
+            Some(job) => {
+                self.shared.finalizers.borrow_mut().push(job);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub(crate) fn set_finalizer_error_handler(&self, handler: FinalizerErrorHandler<'scope>) {
+        *self.shared.finalizer_error_handler.borrow_mut() = Some(handler);
+    }
+
+    /// Takes the errors reported by finalizers so far.
+    pub(crate) fn take_finalizer_errors(&self) -> Vec<FinalizerError> {
+        self.shared.finalizer_errors.take()
     }
 
-    /// Terminate the scope immediately -- all existing jobs will stop at their next await point
-    /// and never wake up again. Anything on their stacks will be dropped. This is most useful
-    /// for propagating errors, but it can be used to propagate any kind of final value (e.g.,
-    /// perhaps you are searching for something and want to stop once you find it.)
+    /// Registers a finalizer that runs when the scope ends, whether it completes
+    /// normally or is terminated. Finalizers run one at a time, most recently registered
+    /// first, after the body and all other jobs are done (or, once the scope is
+    /// terminated, alongside its [shielded](Self::shield) jobs).
     ///
-    /// This returns a future that you should await, but it will never complete
-    /// (because you will never be reawoken). Since termination takes effect at the next
-    /// await point, awaiting the returned future ensures that your current future stops
-    /// immediately.
+    /// A finalizer returns `()` or a `Result`; its errors are reported with
+    /// [`ScopeBody::on_finalizer_error`][crate::ScopeBody::on_finalizer_error] or
+    /// [`ScopeBody::with_finalizer_errors`][crate::ScopeBody::with_finalizer_errors].
+    /// Finalizers do not run if a job panics or if the scope's future is dropped before
+    /// it completes.
     ///
     /// # Examples
     ///
     /// ```rust
     /// # futures::executor::block_on(async {
+    /// let log = std::cell::RefCell::new(vec![]);
     /// let result = moro::async_scope!(|scope| {
-    ///     scope.spawn(async { /* ... */ });
+    ///     scope.defer(async { log.borrow_mut().push("first") });
+    ///     scope.defer(async { log.borrow_mut().push("second") });
+    ///     scope.on_cancel(async { log.borrow_mut().push("cancelled") });
+    ///     scope.terminate("stop").await
+    /// })
+    /// .await;
+    /// assert_eq!(result, "stop");
+    /// assert_eq!(*log.borrow(), ["cancelled", "second", "first"]);
+    /// # });
+    /// ```
+    pub fn defer<O>(&'scope self, finalizer: impl Future<Output = O> + 'scope)
+    where
+        O: FinalizerOutput + 'scope,
+    {
+        self.add_deferred(Finalizer::new(false, finalizer));
+    }
+
+    /// Like [`defer`](Self::defer), but the finalizer only runs if the scope is
+    /// terminated.
+    pub fn on_cancel<O>(&'scope self, finalizer: impl Future<Output = O> + 'scope)
+    where
+        O: FinalizerOutput + 'scope,
+    {
+        self.add_deferred(Finalizer::new(true, finalizer));
+    }
+
+    fn add_deferred(&'scope self, finalizer: Finalizer<'scope>) {
+        self.deferred.borrow_mut().push(finalizer);
+        let mut deferred_job = self.deferred_job.borrow_mut();
+        if deferred_job.is_none() {
+            let future = async move {
+                let finalizers = self.deferred.take();
+                if let Some(future) = run_finalizers(finalizers, self.is_terminated(), &self.shared)
+                {
+                    future.await;
+                }
+            };
+            let id = self.shared.next_job_id();
+            let cell = JobCell::new(
+                Box::pin(future),
+                true,
+                true,
+                &self.shared,
+                Kind::Finalizers(id),
+                None,
+                None,
+            );
+            *deferred_job = Some(Job {
+                id,
+                shielded: true,
+                priority: 0,
+                cell: Rc::new(cell),
+            });
+        }
+    }
+
+    /// Create a [`CancelScope`] region within this scope. Jobs spawned through the
+    /// region can be cancelled together while the rest of the scope keeps running.
+    pub fn cancel_scope(&'scope self) -> CancelScope<'scope, 'env> {
+        CancelScope::new(self)
+    }
+
+    /// Spawn a job that will run concurrently with everything else in the scope.
+    /// The job may access stack fields defined outside the scope.
+    /// The scope will not terminate until this job completes or the scope is cancelled.
+    ///
+    /// # Panics
     ///
-    ///     // Calling `scope.terminate` here will terminate the async
-    ///     // scope and use the string `"cancellation-value"` as
-    ///     // the final value.
-    ///     let result: () = scope.terminate("cancellation-value").await;
-    ///     unreachable!() // this code never executes
+    /// If the job panics, the other jobs in the scope are cancelled (most recently
+    /// spawned first, then the scope body) and the panic is resumed from the scope's
+    /// future. Use [`spawn_catch_unwind`](Self::spawn_catch_unwind) to handle the panic
+    /// from the job's handle instead.
+    ///
+    /// ```rust,should_panic
+    /// # futures::executor::block_on(async {
+    /// moro::async_scope!(|scope| {
+    ///     scope.spawn(async { panic!("boom") }).await
     /// }).await;
+    /// # });
+    /// ```
+    ///
+    /// # Aborted jobs
     ///
-    /// assert_eq!(result, "cancellation-value");
+    /// Awaiting the returned handle yields `Err(Aborted)` if the job was aborted: through
+    /// the handle's [`abort`](Spawned::abort), by a [`CancelScope`], or by a
+    /// [deadline](JobBuilder::deadline).
+    ///
+    /// ```rust
+    /// # futures::executor::block_on(async {
+    /// let result = moro::async_scope!(|scope| {
+    ///     let region = scope.cancel_scope();
+    ///     let job = region.spawn(futures::future::pending::<()>());
+    ///     region.cancel();
+    ///     job.await
+    /// }).await;
+    /// assert_eq!(result, Err(moro::Aborted));
     /// # });
     /// ```
-    pub fn terminate<T>(&'scope self, value: R) -> impl Future<Output = T> + 'scope
+    #[track_caller]
+    pub fn spawn<T>(&'scope self, future: impl Future<Output = T> + 'scope) -> Spawned<'scope, T>
     where
         T: 'scope,
     {
-        let mut lock = self.terminated.lock().unwrap();
-        if lock.is_none() {
-            *lock = Some(value.into());
+        self.spawn_job(future, false, Location::caller())
+    }
+
+    /// Spawn a job that keeps running after the scope is terminated. Use this for
+    /// cleanup that must not be cut short, such as flushing a write or sending a goodbye
+    /// message. Once the scope is terminated, its result is not returned until every
+    /// shielded job has completed, or until the grace period set with
+    /// [`ScopeBody::with_grace_period`][crate::ScopeBody::with_grace_period] runs out.
+    ///
+    /// Otherwise, a shielded job behaves like one spawned with [`spawn`](Self::spawn).
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # futures::executor::block_on(async {
+    /// let flushed = std::cell::Cell::new(false);
+    /// let result = moro::async_scope!(|scope| {
+    ///     scope.shield(async { flushed.set(true) });
+    ///     scope.terminate("stop").await
+    /// }).await;
+    /// assert_eq!(result, "stop");
+    /// assert!(flushed.get());
+    /// # });
+    /// ```
+    #[track_caller]
+    pub fn shield<T>(&'scope self, future: impl Future<Output = T> + 'scope) -> Spawned<'scope, T>
+    where
+        T: 'scope,
+    {
+        self.spawn_job(future, true, Location::caller())
+    }
+
+    /// Spawn a job, first waiting until doing so does not exceed the scope's
+    /// [concurrency limit](crate::ScopeBody::with_max_concurrency). This applies
+    /// backpressure to the spawner; by contrast, [`spawn`](Self::spawn) returns
+    /// immediately and the job waits in a queue until it can run.
+    ///
+    /// Jobs count against the limit from the time they are spawned until they complete,
+    /// so awaiting this from within a job can deadlock if every slot is held by a
+    /// job that is itself waiting here.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+    /// let running = std::cell::Cell::new(0);
+    /// moro::async_scope!(|scope| {
+    ///     for _ in 0..10 {
+    ///         let job = scope.spawn_limited(async {
+    ///             running.set(running.get() + 1);
+    ///             assert!(running.get() <= 2);
+    ///             tokio::task::yield_now().await;
+    ///             running.set(running.get() - 1);
+    ///         });
+    ///         drop(job.await);
+    ///     }
+    /// })
+    /// .with_max_concurrency(2)
+    /// .await;
+    /// # });
+    /// ```
+    // The returned future resolves to the job's handle, which is for the caller to await.
+    #[allow(clippy::async_yields_async)]
+    #[track_caller]
+    pub fn spawn_limited<T>(
+        &'scope self,
+        future: impl Future<Output = T> + 'scope,
+    ) -> impl Future<Output = Spawned<'scope, T>> + 'scope
+    where
+        T: 'scope,
+    {
+        let location = Location::caller();
+        async move {
+            futures::future::poll_fn(|cx| {
+                if self.has_capacity() {
+                    Poll::Ready(())
+                } else {
+                    self.capacity_waiters.borrow_mut().push(cx.waker().clone());
+                    Poll::Pending
+                }
+            })
+            .await;
+            self.spawn_job(future, false, location)
         }
-        std::mem::drop(lock);
+    }
 
-        // The code below will never run
-        self.spawn(async { panic!() })
+    /// Spawns a job, recording `location` as the place it was spawned from.
+    fn spawn_job<T>(
+        &'scope self,
+        future: impl Future<Output = T> + 'scope,
+        shielded: bool,
+        location: &'static Location<'static>,
+    ) -> Spawned<'scope, T>
+    where
+        T: 'scope,
+    {
+        self.spawn_job_with(future, shielded, location, JobOptions::default())
     }
 
-    /// Spawn a job that will run concurrently with everything else in the scope.
-    /// The job may access stack fields defined outside the scope.
-    /// The scope will not terminate until this job completes or the scope is cancelled.
-    pub fn spawn<T>(
+    /// Like [`spawn_job`](Self::spawn_job), with the options of a [`JobBuilder`].
+    pub(crate) fn spawn_job_with<T>(
+        &'scope self,
+        future: impl Future<Output = T> + 'scope,
+        shielded: bool,
+        location: &'static Location<'static>,
+        options: JobOptions,
+    ) -> Spawned<'scope, T>
+    where
+        T: 'scope,
+    {
+        // The job's future lives in a cell shared by the scope and the job's handle.
+        // The scope drives every job, but a handle that is awaited drives its job
+        // directly, rather than waiting for the scope to get around to it.
+        //
+        // If the job panics, the panic is caught and recorded; the scope body then
+        // cancels the other jobs and resumes the panic (see `Body`).
+
+        let admitted = shielded || self.max_concurrency.get().is_none();
+        let id = self.shared.next_job_id();
+        let cell = Rc::new(JobCell::new(
+            Box::pin(future),
+            admitted,
+            shielded,
+            &self.shared,
+            Kind::Job(id),
+            Some(location),
+            options.name,
+        ));
+        if let Some(deadline) = options.deadline {
+            cell.set_deadline(self.timer().sleep(deadline));
+        }
+        let job = Job {
+            id,
+            shielded,
+            priority: options.priority,
+            cell: cell.clone(),
+        };
+        if shielded {
+            self.enqueued_shielded.borrow_mut().push(job);
+        } else {
+            self.live_jobs.set(self.live_jobs.get() + 1);
+            self.enqueued.borrow_mut().push(job);
+        }
+
+        Spawned::new(cell)
+    }
+
+    /// Like [`spawn`](Self::spawn), but if the job panics, the panic is delivered
+    /// to the job's handle as an `Err` instead of being propagated out of the scope.
+    /// The rest of the scope keeps running.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # futures::executor::block_on(async {
+    /// let result = moro::async_scope!(|scope| {
+    ///     let job = scope.spawn_catch_unwind(async { panic!("boom") });
+    ///     job.output().await.is_err()
+    /// }).await;
+    /// assert!(result);
+    /// # });
+    /// ```
+    #[track_caller]
+    pub fn spawn_catch_unwind<T>(
         &'scope self,
         future: impl Future<Output = T> + 'scope,
-    ) -> Spawned<impl Future<Output = T>>
+    ) -> Spawned<'scope, std::thread::Result<T>>
+    where
+        T: 'scope,
+    {
+        self.spawn(AssertUnwindSafe(future).catch_unwind())
+    }
+
+    /// Spawns each of `futures` as a job and waits for the first of them to complete,
+    /// returning its result. The other jobs are aborted, and their futures dropped,
+    /// before the result is returned. Dropping the returned future aborts all of the
+    /// jobs. Use [`race!`](crate::race) to race futures of different types.
+    ///
+    /// # Panics
+    ///
+    /// If `futures` is empty, or if every job is aborted before one completes.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use std::cell::Cell;
+    ///
+    /// /// Counts how many of the racing futures have been dropped.
+    /// struct Dropped<'a>(&'a Cell<u32>);
+    ///
+    /// impl Drop for Dropped<'_> {
+    ///     fn drop(&mut self) {
+    ///         self.0.set(self.0.get() + 1);
+    ///     }
+    /// }
+    ///
+    /// async fn fetch<'a>(replica: &'a str, dropped: &Cell<u32>) -> &'a str {
+    ///     let _dropped = Dropped(dropped);
+    ///     if replica == "slow" {
+    ///         futures::future::pending::<()>().await;
+    ///     }
+    ///     replica
+    /// }
+    ///
+    /// # futures::executor::block_on(async {
+    /// let replicas = ["slow", "fast"];
+    /// let dropped = Cell::new(0);
+    /// let result = moro::infallible_scope!(|scope| {
+    ///     let winner = scope.race(replicas.iter().map(|r| fetch(r, &dropped))).await;
+    ///     assert_eq!(dropped.get(), 2);
+    ///     winner
+    /// })
+    /// .await;
+    /// assert_eq!(result, "fast");
+    /// # });
+    /// ```
+    #[track_caller]
+    pub fn race<T, F>(
+        &'scope self,
+        futures: impl IntoIterator<Item = F>,
+    ) -> impl Future<Output = T> + 'scope
+    where
+        F: Future<Output = T> + 'scope,
+        T: 'scope,
+    {
+        let location = Location::caller();
+        let jobs = futures
+            .into_iter()
+            .map(|f| self.spawn_job(f, false, location));
+        Race::new(jobs.collect())
+    }
+
+    /// Spawns each of `futures` as a job, returning a future that waits for all of them
+    /// and resolves to their results in input order. The jobs start right away, whether
+    /// or not the returned future is awaited.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # futures::executor::block_on(async {
+    /// let inputs = vec![1, 2, 3];
+    /// let result = moro::infallible_scope!(|scope| {
+    ///     scope.spawn_all(inputs.iter().map(|i| async move { i * 2 })).await
+    /// })
+    /// .await;
+    /// assert_eq!(result, [2, 4, 6]);
+    /// # });
+    /// ```
+    #[track_caller]
+    pub fn spawn_all<T, F>(
+        &'scope self,
+        futures: impl IntoIterator<Item = F>,
+    ) -> impl Future<Output = Vec<T>> + 'scope
+    where
+        F: Future<Output = T> + 'scope,
+        T: 'scope,
+    {
+        let location = Location::caller();
+        let jobs: Vec<_> = futures
+            .into_iter()
+            .map(|f| self.spawn_job(f, false, location))
+            .collect();
+        async move {
+            let mut outputs = Vec::with_capacity(jobs.len());
+            // The handles never leave this function, so nothing can abort the jobs.
+            for job in jobs {
+                outputs.push(job.output().await);
+            }
+            outputs
+        }
+    }
+
+    /// Like [`spawn_all`](Self::spawn_all), but for jobs that can fail: as soon as any
+    /// job returns an error, the remaining jobs are aborted and the returned future
+    /// resolves to that error. To terminate the whole scope instead, use
+    /// [`unwrap_or_cancel`][crate::prelude::UnwrapOrCancel] on the result.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// async fn check(input: &i32) -> Result<i32, String> {
+    ///     if *input < 0 {
+    ///         return Err(format!("negative: {input}"));
+    ///     }
+    ///     Ok(*input)
+    /// }
+    ///
+    /// # futures::executor::block_on(async {
+    /// let inputs = vec![1, -2, 3];
+    /// let result = moro::infallible_scope!(|scope| {
+    ///     scope.try_spawn_all(inputs.iter().map(check)).await
+    /// })
+    /// .await;
+    /// assert_eq!(result, Err("negative: -2".to_string()));
+    /// # });
+    /// ```
+    #[track_caller]
+    pub fn try_spawn_all<T, E, F>(
+        &'scope self,
+        futures: impl IntoIterator<Item = F>,
+    ) -> impl Future<Output = Result<Vec<T>, E>> + 'scope
     where
+        F: Future<Output = Result<T, E>> + 'scope,
         T: 'scope,
+        E: 'scope,
     {
-        // Use a channel to communicate result from the *actual* future
-        // (which lives in the futures-unordered) and the caller.
-        // This is kind of crappy because, ideally, the caller expressing interest
-        // in the result of the future would let it run, but that would require
-        // more clever coding and I'm just trying to stand something up quickly
-        // here. What will happen when caller expresses an interest in result
-        // now is that caller will block which should (eventually) allow the
-        // futures-unordered to be polled and make progress. Good enough.
-
-        let (tx, rx) = async_channel::bounded(1);
-
-        self.enqueued.lock().unwrap().push(Box::pin(async move {
-            let v = future.await;
-            let _ = tx.send(v).await;
-        }));
-
-        Spawned::new(async move {
-            match rx.recv().await {
-                Ok(v) => v,
-                Err(e) => panic!("unexpected error: {e:?}"),
+        let location = Location::caller();
+        let mut jobs: Vec<_> = futures
+            .into_iter()
+            .map(|f| Some(self.spawn_job(f, false, location)))
+            .collect();
+        let mut outputs: Vec<Option<T>> = jobs.iter().map(|_| None).collect();
+        futures::future::poll_fn(move |cx| {
+            let mut running = false;
+            for (job, output) in jobs.iter_mut().zip(&mut outputs) {
+                let Some(handle) = job else { continue };
+                match handle.poll_result(cx) {
+                    Poll::Ready(Ok(Ok(v))) => {
+                        *output = Some(v);
+                        *job = None;
+                    }
+                    Poll::Ready(Ok(Err(e))) => {
+                        for handle in jobs.iter().flatten() {
+                            handle.abort();
+                        }
+                        return Poll::Ready(Err(e));
+                    }
+                    // Only this function can abort the jobs, and it returns when it does.
+                    Poll::Ready(Err(Aborted)) => unreachable!(),
+                    Poll::Pending => running = true,
+                }
             }
+            if running {
+                return Poll::Pending;
+            }
+            Poll::Ready(Ok(outputs.drain(..).map(Option::unwrap).collect()))
         })
     }
+
+    /// Creates a [`JobBuilder`] for spawning a job with options, such as a
+    /// [name](JobBuilder::name) for debugging.
+    pub fn build_job(&'scope self) -> JobBuilder<'scope, 'env> {
+        JobBuilder::new(self)
+    }
+
+    /// Creates a [`Supervisor`] that runs jobs in this scope and restarts them when they
+    /// fail.
+    pub fn supervisor<E>(&'scope self) -> Supervisor<'scope, 'env, E>
+    where
+        E: 'scope,
+    {
+        Supervisor::new(self)
+    }
+
+    /// Creates a channel whose endpoints can be moved into the scope's jobs. Up to
+    /// `capacity` messages are buffered; beyond that, [`Sender::send`] waits.
+    ///
+    /// The channel closes once every [`Sender`] has been dropped. Since a job's future is
+    /// dropped as soon as the job finishes, the channel closes once all of the jobs that
+    /// the senders were moved into have finished. It also closes when the scope is
+    /// terminated.
+    ///
+    /// # Panics
+    ///
+    /// If `capacity` is zero.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # futures::executor::block_on(async {
+    /// let result = moro::infallible_scope!(|scope| {
+    ///     let (sender, mut receiver) = scope.channel(2);
+    ///     for producer in 0..3 {
+    ///         let sender = sender.clone();
+    ///         scope.spawn(async move { sender.send(producer).await.unwrap() });
+    ///     }
+    ///
+    ///     // Forgetting this would leave `sender` in the scope body, which is also where
+    ///     // the messages are received.
+    ///     drop(sender);
+    ///
+    ///     let mut total = 0;
+    ///     while let Ok(n) = receiver.recv().await {
+    ///         total += n;
+    ///     }
+    ///     total
+    /// })
+    /// .await;
+    /// assert_eq!(result, 3);
+    /// # });
+    /// ```
+    ///
+    /// Without the `drop`, `sender` would stay with the body, which waits to receive, so
+    /// the channel would never close. Rather than wait forever once the producers have
+    /// finished, `recv` then fails with [`RecvError::Stalled`][crate::RecvError::Stalled]:
+    ///
+    /// ```rust
+    /// # futures::executor::block_on(async {
+    /// let result = moro::infallible_scope!(|scope| {
+    ///     let (sender, mut receiver) = scope.channel(2);
+    ///     let producer = sender.clone();
+    ///     scope.spawn(async move { producer.send(1).await.unwrap() });
+    ///     let mut received = vec![];
+    ///     let error = loop {
+    ///         match receiver.recv().await {
+    ///             Ok(n) => received.push(n),
+    ///             Err(error) => break error,
+    ///         }
+    ///     };
+    ///     (received, error)
+    /// })
+    /// .await;
+    /// assert_eq!(result, (vec![1], moro::RecvError::Stalled));
+    /// # });
+    /// ```
+    ///
+    /// The scope only fails a receive once it has stalled: once neither its body nor any
+    /// of its jobs can be woken, except by its own channels and job handles. As long as a
+    /// job that holds a sender waits on anything else, e.g. a timer, the receive waits.
+    pub fn channel<T>(&'scope self, capacity: usize) -> (Sender<'scope, T>, Receiver<'scope, T>)
+    where
+        T: 'scope,
+    {
+        let (sender, receiver) = channel::channel(capacity);
+        self.add_channel(receiver.channel());
+        (sender, receiver)
+    }
+
+    /// Creates a channel for a single value. Awaiting the receiver yields the value, or
+    /// an error if the sender is dropped without sending, or if the scope is terminated.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # futures::executor::block_on(async {
+    /// let result = moro::infallible_scope!(|scope| {
+    ///     let (sender, receiver) = scope.oneshot();
+    ///     scope.spawn(async move { sender.send("ready").unwrap() });
+    ///     receiver.await
+    /// })
+    /// .await;
+    /// assert_eq!(result, Ok("ready"));
+    /// # });
+    /// ```
+    ///
+    /// Oneshots can carry replies from a job that serves requests:
+    ///
+    /// ```rust
+    /// # futures::executor::block_on(async {
+    /// let result = moro::infallible_scope!(|scope| {
+    ///     let (requests, mut incoming) = scope.channel::<(i32, moro::OneshotSender<'_, i32>)>(1);
+    ///     scope.spawn(async move {
+    ///         while let Ok((n, reply)) = incoming.recv().await {
+    ///             reply.send(n * 2).unwrap();
+    ///         }
+    ///     });
+    ///
+    ///     let (reply, response) = scope.oneshot();
+    ///     requests.send((21, reply)).await.unwrap();
+    ///     response.await
+    /// })
+    /// .await;
+    /// assert_eq!(result, Ok(42));
+    /// # });
+    /// ```
+    pub fn oneshot<T>(&'scope self) -> (OneshotSender<'scope, T>, OneshotReceiver<'scope, T>)
+    where
+        T: 'scope,
+    {
+        let (sender, receiver) = channel::oneshot();
+        self.add_channel(receiver.channel());
+        (sender, receiver)
+    }
+
+    fn add_channel(&self, channel: Weak<dyn Channel + 'scope>) {
+        let mut channels = self.channels.borrow_mut();
+        channels.retain(|channel| channel.strong_count() > 0);
+        channels.push(channel);
+    }
+
+    /// Takes a snapshot of the jobs in this scope, including the jobs of scopes nested
+    /// within them, for debugging. See [`ScopeDump`] for what it contains.
+    ///
+    /// Returns `None` unless the scope records its jobs in a task tree, which it does
+    /// with [`ScopeBody::with_task_tree`][crate::ScopeBody::with_task_tree].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+    /// let dump = moro::infallible_scope!(|scope| {
+    ///     scope.spawn(async {
+    ///         moro::infallible_scope!(|inner| {
+    ///             inner.spawn(tokio::task::yield_now());
+    ///         })
+    ///         .await
+    ///     });
+    ///     tokio::task::yield_now().await;
+    ///     scope.dump().unwrap()
+    /// })
+    /// .with_task_tree()
+    /// .await;
+    ///
+    /// let text = dump.to_string();
+    /// let lines: Vec<&str> = text.lines().collect();
+    /// assert!(lines[0].starts_with("scope at "));
+    /// assert!(lines[1].starts_with("  job 0 at "));
+    /// assert!(lines[2].starts_with("    scope at "));
+    /// assert!(lines[3].starts_with("      job 0 at "));
+    /// assert!(lines[3].contains(": pending, polled once, "));
+    ///
+    /// assert!(dump.to_json().starts_with(r#"{"kind":"scope","id":null,"#));
+    /// # });
+    /// ```
+    pub fn dump(&self) -> Option<ScopeDump> {
+        self.record().map(ScopeDump::new)
+    }
+}
+
+/// How a job is referred to in deadlock reports.
+fn job_label(job: &Job<'_>) -> String {
+    match job.cell.name() {
+        Some(name) => format!("job {} {name:?}", job.id),
+        None => format!("job {}", job.id),
+    }
 }
@@ -1,14 +1,18 @@
-use std::pin::Pin;
+use std::{pin::Pin, rc::Rc, task::Poll, time::Duration};
 
-use futures::Future;
+use futures::{future::poll_fn, Future};
 use pin_project::pin_project;
 
-use crate::body::Body;
+use crate::{
+    body::Body,
+    time::{Elapsed, Timer},
+    ErrorMode, FinalizerError, MultiError,
+};
 
 #[pin_project]
-pub struct ScopeBody<'env, R: 'env, F>
+pub struct ScopeBody<'env, R, F>
 where
-    R: Send,
+    R: 'env,
     F: Future<Output = R>,
 {
     #[pin]
@@ -17,17 +21,355 @@ where
 
 impl<'env, R, F> ScopeBody<'env, R, F>
 where
-    R: Send,
     F: Future<Output = R>,
 {
     pub(crate) fn new(body: Body<'env, 'env, R, F>) -> Self {
         Self { body }
     }
+
+    /// Bounds how long [shielded](crate::Scope::shield) jobs may keep running once the
+    /// scope is terminated. `grace_period` is invoked when the scope is terminated; if
+    /// the future it returns completes before the shielded jobs do, they are dropped
+    /// and the scope returns its terminal value.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # futures::executor::block_on(async {
+    /// let result = moro::async_scope!(|scope| {
+    ///     scope.shield(futures::future::pending::<()>());
+    ///     scope.terminate("stop").await
+    /// })
+    /// .with_grace_period(|| async {})
+    /// .await;
+    /// assert_eq!(result, "stop");
+    /// # });
+    /// ```
+    pub fn with_grace_period<G>(self, grace_period: impl FnOnce() -> G + 'env) -> Self
+    where
+        G: Future<Output = ()> + 'env,
+    {
+        self.body
+            .scope()
+            .set_grace_period(Box::new(move || Box::pin(grace_period())));
+        self
+    }
+
+    /// Limits the number of jobs in the scope that run at once. Jobs spawned beyond the
+    /// limit wait in a queue and start, in the order they were spawned, as running jobs
+    /// complete. Use [`Scope::spawn_limited`][crate::Scope::spawn_limited] to instead make
+    /// the spawner wait. [Shielded](crate::Scope::shield) jobs are not limited.
+    ///
+    /// # Panics
+    ///
+    /// If `max_concurrency` is zero.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+    /// let running = std::cell::Cell::new(0);
+    /// moro::async_scope!(|scope| {
+    ///     for _ in 0..10 {
+    ///         scope.spawn(async {
+    ///             running.set(running.get() + 1);
+    ///             assert!(running.get() <= 3);
+    ///             tokio::task::yield_now().await;
+    ///             running.set(running.get() - 1);
+    ///         });
+    ///     }
+    /// })
+    /// .with_max_concurrency(3)
+    /// .await;
+    /// # });
+    /// ```
+    pub fn with_max_concurrency(self, max_concurrency: usize) -> Self {
+        self.body.scope().set_max_concurrency(max_concurrency);
+        self
+    }
+
+    /// Debug mode that panics, describing the problem, if the scope deadlocks: if the
+    /// body and every job are pending, and nothing but the handles of other jobs in the
+    /// scope, or its channels, can wake them.
+    ///
+    /// To tell, the scope polls each job (and the body) with a waker of its own, whose
+    /// clones are counted. A clone held by a job's handle means that the job (or body) that
+    /// registered it waits on that job. A clone held by one of the scope's
+    /// [channels](crate::Scope::channel) means that it waits to receive or send, which only
+    /// the scope's own jobs can make possible. Clones held anywhere else, e.g. by a timer,
+    /// another channel or the scope's concurrency limit, might wake the job, so the scope
+    /// is not considered stalled while any exist. A stalled scope is reported even if a deadline
+    /// set with [`move_on_after`](Self::move_on_after) would later end it.
+    ///
+    /// The scope always keeps track of this, so with or without this mode, a stalled scope
+    /// in which a job waits to receive on one of its channels fails that receive with
+    /// [`RecvError::Stalled`][crate::RecvError::Stalled] instead. This mode reports the
+    /// other deadlocks, which would otherwise hang.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,should_panic
+    /// # futures::executor::block_on(async {
+    /// use std::{cell::RefCell, rc::Rc};
+    ///
+    /// moro::infallible_scope!(|scope| {
+    ///     let second: Rc<RefCell<Option<moro::Spawned<'_, ()>>>> = Default::default();
+    ///     let first = scope.spawn({
+    ///         let second = second.clone();
+    ///         async move { second.take().unwrap().output().await }
+    ///     });
+    ///     *second.borrow_mut() = Some(scope.spawn(async { first.output().await }));
+    /// })
+    /// .with_deadlock_detection()
+    /// .await;
+    /// // panics with "deadlock in moro scope: job 0 waits on job 1, which waits on job 0"
+    /// # });
+    /// ```
+    pub fn with_deadlock_detection(self) -> Self {
+        self.body.scope().set_detect_deadlocks();
+        self
+    }
+
+    /// Records the scope's jobs in a task tree, so that [`Scope::dump`][crate::Scope::dump]
+    /// can describe them. Scopes created by the jobs of this scope are recorded too. The
+    /// tree costs some time on every spawn and poll, so scopes keep none by default.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # futures::executor::block_on(async {
+    /// let dumps = moro::infallible_scope!(|scope| scope.dump().is_some()).await;
+    /// assert!(!dumps);
+    ///
+    /// let dumps = moro::infallible_scope!(|scope| scope.dump().is_some())
+    ///     .with_task_tree()
+    ///     .await;
+    /// assert!(dumps);
+    /// # });
+    /// ```
+    pub fn with_task_tree(self) -> Self {
+        self.body.scope().set_record_tasks();
+        self
+    }
+
+    /// Sets the [`Timer`] used for deadlines within this scope.
+    pub fn with_timer(self, timer: impl Timer + 'static) -> Self {
+        self.body.scope().set_timer(Rc::new(timer));
+        self
+    }
+
+    /// Calls `handler` with each error returned by a finalizer (see
+    /// [`Scope::defer`][crate::Scope::defer]), as soon as the finalizer fails.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # futures::executor::block_on(async {
+    /// let mut failures = vec![];
+    /// moro::infallible_scope!(|scope| {
+    ///     scope.defer(async { Err("could not flush") });
+    /// })
+    /// .on_finalizer_error(|error| failures.push(error.to_string()))
+    /// .await;
+    /// assert_eq!(failures, ["could not flush"]);
+    /// # });
+    /// ```
+    pub fn on_finalizer_error(self, handler: impl FnMut(FinalizerError) + 'env) -> Self {
+        self.body
+            .scope()
+            .set_finalizer_error_handler(Box::new(handler));
+        self
+    }
+
+    /// Runs the scope, returning its result along with the errors returned by its
+    /// finalizers (see [`Scope::defer`][crate::Scope::defer]), in the order they occurred.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # futures::executor::block_on(async {
+    /// let (result, errors) = moro::infallible_scope!(|scope| {
+    ///     scope.defer(async { Err("could not flush") });
+    ///     22
+    /// })
+    /// .with_finalizer_errors()
+    /// .await;
+    /// assert_eq!(result, 22);
+    /// assert_eq!(errors[0].to_string(), "could not flush");
+    /// # });
+    /// ```
+    pub async fn with_finalizer_errors(self) -> (R, Vec<FinalizerError>) {
+        let mut this = std::pin::pin!(self);
+        let result = this.as_mut().await;
+        (result, this.body.scope().take_finalizer_errors())
+    }
+
+    /// Runs the scope with a deadline, measured by the scope's [timer](Self::with_timer)
+    /// from when the scope is first polled. If `duration` elapses before the scope
+    /// completes, the scope is terminated as if by
+    /// [`terminate`](crate::CancellableScope::terminate), and the result is `None` once
+    /// its [shielded](crate::Scope::shield) jobs and [finalizers](crate::Scope::defer)
+    /// have run.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use std::time::Duration;
+    /// use futures::future::LocalBoxFuture;
+    ///
+    /// /// A timer whose deadlines have always passed already.
+    /// struct Expired;
+    ///
+    /// impl moro::time::Timer for Expired {
+    ///     fn sleep(&self, _: Duration) -> LocalBoxFuture<'static, ()> {
+    ///         Box::pin(async {})
+    ///     }
+    /// }
+    ///
+    /// # futures::executor::block_on(async {
+    /// let result = moro::async_scope!(|scope| {
+    ///     scope.spawn(futures::future::pending::<()>()).await
+    /// })
+    /// .with_timer(Expired)
+    /// .move_on_after(Duration::from_secs(1))
+    /// .await;
+    /// assert_eq!(result, None);
+    /// # });
+    /// ```
+    ///
+    /// Shielded jobs and finalizers run as they would after `terminate`:
+    ///
+    /// ```rust
+    /// # use std::time::Duration;
+    /// # use futures::future::LocalBoxFuture;
+    /// # struct Expired;
+    /// # impl moro::time::Timer for Expired {
+    /// #     fn sleep(&self, _: Duration) -> LocalBoxFuture<'static, ()> {
+    /// #         Box::pin(async {})
+    /// #     }
+    /// # }
+    /// # futures::executor::block_on(async {
+    /// let (flushed, deferred) = (std::cell::Cell::new(false), std::cell::Cell::new(false));
+    /// let result = moro::async_scope!(|scope| {
+    ///     scope.shield(async { flushed.set(true) });
+    ///     scope.defer(async { deferred.set(true) });
+    ///     scope.spawn(futures::future::pending::<()>()).await
+    /// })
+    /// .with_timer(Expired)
+    /// .move_on_after(Duration::from_secs(1))
+    /// .await;
+    /// assert_eq!((result, flushed.get(), deferred.get()), (None, true, true));
+    /// # });
+    /// ```
+    pub async fn move_on_after(self, duration: Duration) -> Option<R>
+    where
+        F: 'env,
+    {
+        let mut body = std::pin::pin!(self.body);
+        // The clock starts when the scope is first polled.
+        let mut sleep = None;
+        poll_fn(|cx| {
+            if let Poll::Ready(r) = body.as_mut().poll_ended(cx) {
+                return Poll::Ready(r);
+            }
+            let scope = body.scope();
+            if scope.is_terminated() {
+                return Poll::Pending;
+            }
+            let sleep = sleep.get_or_insert_with(|| scope.timer().sleep(duration));
+            ready!(sleep.as_mut().poll(cx));
+
+            // End the scope as `terminate` would, so that shielded jobs and
+            // finalizers still run, just without a terminal value.
+            scope.set_terminated();
+            body.as_mut().poll_ended(cx)
+        })
+        .await
+    }
+
+    /// Like [`move_on_after`](Self::move_on_after), but returns `Err(Elapsed)` if the
+    /// deadline passes first.
+    pub fn fail_after(self, duration: Duration) -> impl Future<Output = Result<R, Elapsed>> + 'env
+    where
+        F: 'env,
+    {
+        let future = self.move_on_after(duration);
+        async move { future.await.ok_or(Elapsed) }
+    }
+}
+
+impl<'env, T, E, F> ScopeBody<'env, Result<T, E>, F>
+where
+    T: 'env,
+    E: 'env,
+    F: Future<Output = Result<T, E>> + 'env,
+{
+    /// Runs the scope in the given [`ErrorMode`], gathering its errors into a
+    /// [`MultiError`].
+    ///
+    /// With [`ErrorMode::FailFast`], the scope behaves as usual and an error result holds
+    /// exactly one error. With [`ErrorMode::CollectAll`], terminating the scope with an
+    /// error (including through [`unwrap_or_cancel`][crate::prelude::UnwrapOrCancel] or
+    /// [`or_cancel`][crate::Spawned::or_cancel]) stops only the job that did so, and the
+    /// result holds every error, in the order they were reported, followed by the error
+    /// returned by the body if any.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use moro::ErrorMode;
+    ///
+    /// async fn check(input: &i32) -> Result<(), String> {
+    ///     if *input < 0 {
+    ///         return Err(format!("negative: {input}"));
+    ///     }
+    ///     Ok(())
+    /// }
+    ///
+    /// # futures::executor::block_on(async {
+    /// let inputs = vec![1, -2, 3, -4];
+    /// let result = moro::async_scope!(|scope| -> Result<(), String> {
+    ///     for input in &inputs {
+    ///         drop(scope.spawn(check(input)).or_cancel(scope));
+    ///     }
+    ///     Ok(())
+    /// })
+    /// .with_error_mode(ErrorMode::CollectAll)
+    /// .await;
+    /// assert_eq!(result.unwrap_err().into_errors(), ["negative: -2", "negative: -4"]);
+    /// # });
+    /// ```
+    pub fn with_error_mode(
+        self,
+        mode: ErrorMode,
+    ) -> impl Future<Output = Result<T, MultiError<E>>> + 'env {
+        if mode == ErrorMode::CollectAll {
+            self.body.scope().set_collect(Result::is_err);
+        }
+        async move {
+            let mut this = std::pin::pin!(self);
+            let result = this.as_mut().await;
+            let mut errors: Vec<E> = this
+                .body
+                .scope()
+                .take_collected()
+                .into_iter()
+                .filter_map(Result::err)
+                .collect();
+            match result {
+                Ok(v) if errors.is_empty() => Ok(v),
+                Ok(_) => Err(MultiError::new(errors)),
+                Err(e) => {
+                    errors.push(e);
+                    Err(MultiError::new(errors))
+                }
+            }
+        }
+    }
 }
 
 impl<'env, R, F> Future for ScopeBody<'env, R, F>
 where
-    R: Send,
     F: Future<Output = R>,
 {
     type Output = R;
@@ -0,0 +1,182 @@
+//! Sources of [`Stream`]s and [`AsyncIterator`]s, and bridges to and from
+//! [`futures::Stream`].
+//!
+//! The sources are wrapper types rather than implementations on the wrapped types, so
+//! that e.g. `next` on a range or a channel keeps meaning what it did before.
+
+use std::ops::{ControlFlow, Range};
+
+use futures::StreamExt;
+
+use crate::{AsyncIterator, Stream};
+
+/// Creates a source that yields the items of `iter`.
+///
+/// # Examples
+///
+/// ```rust
+/// #![feature(async_trait_bounds)]
+/// use moro::Stream;
+///
+/// # futures::executor::block_on(async {
+/// let sum = moro::source::iter(vec![1, 2, 3])
+///     .fold(0, async |sum, n| sum + n)
+///     .await;
+/// assert_eq!(sum, 6);
+/// # });
+/// ```
+pub fn iter<I: IntoIterator>(iter: I) -> Iter<I::IntoIter> {
+    Iter {
+        iter: iter.into_iter(),
+    }
+}
+
+/// Creates a source that yields the values in `range`.
+///
+/// # Examples
+///
+/// ```rust
+/// use moro::AsyncIterator;
+///
+/// # futures::executor::block_on(async {
+/// let mut numbers = moro::source::range(0..2);
+/// assert_eq!(numbers.next().await, Some(0));
+/// assert_eq!(numbers.next().await, Some(1));
+/// assert_eq!(numbers.next().await, None);
+/// # });
+/// ```
+pub fn range<A>(range: Range<A>) -> Iter<Range<A>>
+where
+    Range<A>: Iterator,
+{
+    Iter { iter: range }
+}
+
+/// Creates a source that yields the messages received on `receiver`, until the channel is
+/// closed.
+///
+/// # Examples
+///
+/// ```rust
+/// #![feature(async_trait_bounds)]
+/// use moro::Stream;
+///
+/// # futures::executor::block_on(async {
+/// let (sender, receiver) = async_channel::unbounded();
+/// let messages = moro::infallible_scope!(|scope| {
+///     scope.spawn(async move {
+///         for message in ["hello", "world"] {
+///             sender.send(message).await.unwrap();
+///         }
+///     });
+///     moro::source::receiver(receiver)
+///         .fold(vec![], async |mut messages, message| {
+///             messages.push(message);
+///             messages
+///         })
+///         .await
+/// })
+/// .await;
+/// assert_eq!(messages, ["hello", "world"]);
+/// # });
+/// ```
+pub fn receiver<T>(receiver: async_channel::Receiver<T>) -> Receiver<T> {
+    Receiver { receiver }
+}
+
+/// Creates a source that yields the items of a [`futures::Stream`]. To go the other way,
+/// see [`AsyncIterator::into_futures_stream`] and [`Stream::into_futures_stream`].
+///
+/// # Examples
+///
+/// ```rust
+/// use moro::AsyncIterator;
+///
+/// # futures::executor::block_on(async {
+/// let mut items = moro::source::from_stream(futures::stream::iter(["a", "b"]));
+/// assert_eq!(items.next().await, Some("a"));
+/// assert_eq!(items.next().await, Some("b"));
+/// assert_eq!(items.next().await, None);
+/// # });
+/// ```
+pub fn from_stream<S: futures::Stream + Unpin>(stream: S) -> FromStream<S> {
+    FromStream { stream }
+}
+
+/// Source returned by [`iter`] and [`range`].
+pub struct Iter<I> {
+    iter: I,
+}
+
+impl<I: Iterator> AsyncIterator for Iter<I> {
+    type Item = I::Item;
+
+    async fn next(&mut self) -> Option<Self::Item> {
+        self.iter.next()
+    }
+}
+
+impl<I: Iterator> Stream for Iter<I> {
+    async fn try_fold<R>(
+        &mut self,
+        mut acc: R,
+        mut op: impl async FnMut(R, Self::Item) -> ControlFlow<R, R>,
+    ) -> ControlFlow<R, R> {
+        for item in &mut self.iter {
+            acc = op(acc, item).await?;
+        }
+        ControlFlow::Continue(acc)
+    }
+}
+
+/// Source returned by [`receiver`].
+pub struct Receiver<T> {
+    receiver: async_channel::Receiver<T>,
+}
+
+impl<T> AsyncIterator for Receiver<T> {
+    type Item = T;
+
+    async fn next(&mut self) -> Option<Self::Item> {
+        self.receiver.recv().await.ok()
+    }
+}
+
+impl<T> Stream for Receiver<T> {
+    async fn try_fold<R>(
+        &mut self,
+        mut acc: R,
+        mut op: impl async FnMut(R, Self::Item) -> ControlFlow<R, R>,
+    ) -> ControlFlow<R, R> {
+        while let Ok(item) = self.receiver.recv().await {
+            acc = op(acc, item).await?;
+        }
+        ControlFlow::Continue(acc)
+    }
+}
+
+/// Source returned by [`from_stream`].
+pub struct FromStream<S> {
+    stream: S,
+}
+
+impl<S: futures::Stream + Unpin> AsyncIterator for FromStream<S> {
+    type Item = S::Item;
+
+    async fn next(&mut self) -> Option<Self::Item> {
+        self.stream.next().await
+    }
+}
+
+impl<S: futures::Stream + Unpin> Stream for FromStream<S> {
+    async fn try_fold<R>(
+        &mut self,
+        mut acc: R,
+        mut op: impl async FnMut(R, Self::Item) -> ControlFlow<R, R>,
+    ) -> ControlFlow<R, R> {
+        while let Some(item) = self.stream.next().await {
+            acc = op(acc, item).await?;
+        }
+        ControlFlow::Continue(acc)
+    }
+}
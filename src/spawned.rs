@@ -1,51 +1,253 @@
-use std::pin::Pin;
+use std::time::Duration;
 
+use std::{
+    cell::OnceCell,
+    rc::{Rc, Weak},
+};
+
+use crate::finalizer::Finalizer;
+use crate::job::{ErasedJob, JobCell};
 use crate::prelude::*;
-use crate::Scope;
-use futures::Future;
+use crate::time::Elapsed;
+use crate::{CancellableScope, FinalizerOutput, Scope};
+use futures::{
+    future::{select, Either},
+    Future,
+};
+
+/// Handle to a job spawned with [`Scope::spawn`].
+///
+/// Awaiting the handle yields the job's result, or `Err(Aborted)` if the job was
+/// aborted: through its handle, by cancelling the [`CancelScope`][crate::CancelScope] it
+/// was spawned in, or because its [deadline](crate::JobBuilder::deadline) passed. For a
+/// job that none of these can happen to, [`output`](Spawned::output) waits for the
+/// result itself. The handle can also be used to [abort](Spawned::abort) the job without
+/// affecting the rest of the scope.
+///
+/// While the handle is awaited, it drives the job directly; the scope keeps driving
+/// the job as well, so the job makes progress whether or not its handle is awaited.
+pub struct Spawned<'scope, T> {
+    cell: Rc<JobCell<'scope, T>>,
+}
 
-pub struct Spawned<F> {
-    f: F,
+/// Error returned by awaiting a [`Spawned`] handle when the job was aborted before it
+/// completed.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Aborted;
+
+impl std::fmt::Display for Aborted {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "job was aborted")
+    }
 }
 
-impl<F> Spawned<F> {
-    pub(crate) fn new(f: F) -> Self {
-        Self { f }
+impl std::error::Error for Aborted {}
+
+impl<'scope, T> Spawned<'scope, T> {
+    pub(crate) fn new(cell: Rc<JobCell<'scope, T>>) -> Self {
+        Self { cell }
+    }
+
+    /// The job, with its output type erased, so that it can be aborted later.
+    pub(crate) fn job(&self) -> Rc<dyn ErasedJob + 'scope>
+    where
+        T: 'scope,
+    {
+        self.cell.clone()
+    }
+
+    /// Polls for the job's result, as awaiting the handle does, but through a shared
+    /// reference.
+    pub(crate) fn poll_result(
+        &self,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Result<T, Aborted>> {
+        self.cell.poll_output(cx)
+    }
+
+    /// Abort the job. The job's future is dropped right away (or, if the job aborts
+    /// itself, as soon as it yields); the rest of the scope keeps running. If the job
+    /// already completed, its result remains available.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # futures::executor::block_on(async {
+    /// let result = moro::async_scope!(|scope| {
+    ///     let job = scope.spawn(futures::future::pending::<()>());
+    ///     job.abort();
+    ///     job.await
+    /// }).await;
+    /// assert_eq!(result, Err(moro::Aborted));
+    /// # });
+    /// ```
+    pub fn abort(&self) {
+        self.cell.abort();
+    }
+
+    /// Registers a finalizer that runs once the job ends, whether it completes, is
+    /// aborted, or is cancelled because the scope was terminated. The job's finalizers
+    /// run as a [shielded](Scope::shield) job, one at a time, most recently registered
+    /// first. If the job has already ended, the finalizer is started right away.
+    ///
+    /// See [`Scope::defer`] for how finalizer errors are reported.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # futures::executor::block_on(async {
+    /// let log = std::cell::RefCell::new(vec![]);
+    /// let result = moro::async_scope!(|scope| {
+    ///     let job = scope.spawn(futures::future::pending::<()>());
+    ///     job.defer(async { log.borrow_mut().push("job finalized") });
+    ///     job.on_cancel(async { log.borrow_mut().push("job cancelled") });
+    ///     scope.terminate("stop").await
+    /// })
+    /// .await;
+    /// assert_eq!(result, "stop");
+    /// assert_eq!(*log.borrow(), ["job cancelled", "job finalized"]);
+    /// # });
+    /// ```
+    pub fn defer<O>(&self, finalizer: impl Future<Output = O> + 'scope)
+    where
+        O: FinalizerOutput + 'scope,
+    {
+        self.cell.add_finalizer(Finalizer::new(false, finalizer));
+    }
+
+    /// Like [`defer`](Self::defer), but the finalizer only runs if the job does not
+    /// complete: that is, if it is aborted, panics, or is cancelled because the scope
+    /// was terminated.
+    pub fn on_cancel<O>(&self, finalizer: impl Future<Output = O> + 'scope)
+    where
+        O: FinalizerOutput + 'scope,
+    {
+        self.cell.add_finalizer(Finalizer::new(true, finalizer));
+    }
+
+    /// True if the job is no longer running, either because it completed or because
+    /// it was aborted.
+    pub fn is_finished(&self) -> bool {
+        self.cell.is_finished()
+    }
+
+    /// Takes the job's result if it has completed, without waiting.
+    ///
+    /// Returns `None` if the job is still running, was aborted, or its result has already
+    /// been taken.
+    pub fn try_take_output(&mut self) -> Option<T> {
+        self.cell.try_take_output()
+    }
+
+    /// Waits for the job to complete and returns its result, for a job that cannot be
+    /// aborted. Awaiting the handle itself returns `Err(Aborted)` instead.
+    ///
+    /// # Panics
+    ///
+    /// If the job was aborted.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # futures::executor::block_on(async {
+    /// let result = moro::infallible_scope!(|scope| {
+    ///     scope.spawn(async { 22 }).output().await
+    /// }).await;
+    /// assert_eq!(result, 22);
+    /// # });
+    /// ```
+    pub async fn output(self) -> T {
+        let cell = self.cell.clone();
+        match self.await {
+            Ok(output) => output,
+            Err(Aborted) => match cell.name() {
+                Some(name) => panic!("job {name:?} was aborted"),
+                None => panic!("job was aborted"),
+            },
+        }
+    }
+
+    /// Waits for the job with a deadline measured by the scope's
+    /// [timer](crate::ScopeBody::with_timer). If `duration` elapses first, the job is
+    /// aborted and the result is `None`. The result is also `None` if the job is aborted
+    /// some other way.
+    pub fn move_on_after(
+        self,
+        scope: &Scope<'_, '_>,
+        duration: Duration,
+    ) -> impl Future<Output = Option<T>> + 'scope
+    where
+        T: 'scope,
+    {
+        let job = self.job();
+        let timer = scope.timer();
+        async move {
+            // The clock starts when the returned future is first polled.
+            let sleep = timer.sleep(duration);
+            match select(self, sleep).await {
+                Either::Left((v, _)) => v.ok(),
+                Either::Right(((), _)) => {
+                    job.abort();
+                    None
+                }
+            }
+        }
+    }
+
+    /// Like [`move_on_after`](Self::move_on_after), but returns `Err(Elapsed)` if the
+    /// deadline passes first.
+    pub fn fail_after(
+        self,
+        scope: &Scope<'_, '_>,
+        duration: Duration,
+    ) -> impl Future<Output = Result<T, Elapsed>> + 'scope
+    where
+        T: 'scope,
+    {
+        let future = self.move_on_after(scope, duration);
+        async move { future.await.ok_or(Elapsed) }
     }
 }
 
-impl<F> Future for Spawned<F>
-where
-    F: Future,
-{
-    type Output = F::Output;
+impl<T> Future for Spawned<'_, T> {
+    type Output = Result<T, Aborted>;
 
     fn poll(
         self: std::pin::Pin<&mut Self>,
         cx: &mut std::task::Context<'_>,
     ) -> std::task::Poll<Self::Output> {
-        unsafe {
-            let f = Pin::new_unchecked(&mut self.get_unchecked_mut().f);
-            F::poll(f, cx)
-        }
+        self.poll_result(cx)
     }
 }
 
-impl<F, O, E> Spawned<F>
-where
-    F: Future<Output = Result<O, E>> + Send,
-    O: Send,
-    E: Send,
-{
-    pub fn or_cancel<'scope, 'env, T>(
+impl<'scope, O, E> Spawned<'scope, Result<O, E>> {
+    /// Waits for the job in a new job, terminating `scope` if the job fails. The error is
+    /// converted with `From` (just like the `?` operator). If the job is aborted, so is
+    /// the new job.
+    pub fn or_cancel<'env, T, E2>(
         self,
-        scope: &'scope Scope<'scope, 'env, Result<T, E>>,
-    ) -> impl Future<Output = O> + 'scope
+        scope: &'scope CancellableScope<'scope, 'env, Result<T, E2>>,
+    ) -> Spawned<'scope, O>
     where
-        T: Send,
+        E2: From<E>,
         O: 'scope,
-        F: 'scope,
+        E: 'scope,
     {
-        scope.spawn(async { self.await.unwrap_or_cancel(scope).await })
+        // Lets the new job abort itself.
+        let cell: Rc<OnceCell<Weak<dyn ErasedJob + 'scope>>> = Default::default();
+        let this = cell.clone();
+        let spawned = scope.spawn(async move {
+            match self.await {
+                Ok(result) => result.unwrap_or_cancel(scope).await,
+                Err(Aborted) => {
+                    if let Some(job) = this.get().and_then(Weak::upgrade) {
+                        job.abort();
+                    }
+                    futures::future::pending().await
+                }
+            }
+        });
+        let _ = cell.set(Rc::downgrade(&spawned.job()));
+        spawned
     }
 }
@@ -1,4 +1,50 @@
-use crate::{AsyncIterator, IntoAsyncIter, Scope};
+use std::ops::ControlFlow;
+
+use futures::Future;
+
+use crate::{
+    concurrent::{self, MapConcurrent},
+    merge::Merge,
+    AsyncIterator, IntoAsyncIter, Order, Scope,
+};
+
+/// Spawns each of `streams` into `scope` as a producer job and merges their items into a
+/// single stream, which ends once every producer has.
+///
+/// The items are interleaved fairly: each producer can get one item ahead of the consumer
+/// and then waits for it to be taken, and the consumer takes items from the producers in
+/// turn. Dropping the merged stream aborts the producers, as does terminating the scope.
+///
+/// # Examples
+///
+/// ```rust
+/// use moro::Stream;
+///
+/// # futures::executor::block_on(async {
+/// let evens = [0, 2, 4];
+/// let odds = [1, 3, 5];
+/// let result: Vec<&i32> = moro::infallible_scope!(|scope| {
+///     moro::select_all(scope, [moro::source::iter(&evens), moro::source::iter(&odds)])
+///         .collect()
+///         .await
+/// })
+/// .await;
+/// assert_eq!(result, [&0, &1, &2, &3, &4, &5]);
+/// # });
+/// ```
+pub fn select_all<'scope, S>(
+    scope: &'scope Scope<'scope, '_>,
+    streams: impl IntoIterator<Item = S>,
+) -> impl Stream<Item = S::Item> + 'scope
+where
+    S: Stream + 'scope,
+{
+    let mut merge = Merge::new();
+    for stream in streams {
+        merge.add(scope, stream);
+    }
+    merge
+}
 
 pub trait Stream: IntoAsyncIter {
     fn filter(self, op: impl async FnMut(&Self::Item) -> bool) -> impl Stream<Item = Self::Item>
@@ -11,6 +57,132 @@ pub trait Stream: IntoAsyncIter {
         }
     }
 
+    /// Applies `op` to each item.
+    ///
+    /// ```rust
+    /// use moro::Stream;
+    ///
+    /// # futures::executor::block_on(async {
+    /// let v: Vec<_> = moro::source::range(1..3).map(|n| n * 10).collect().await;
+    /// assert_eq!(v, [10, 20]);
+    /// # });
+    /// ```
+    fn map<U>(self, op: impl FnMut(Self::Item) -> U) -> impl Stream<Item = U>
+    where
+        Self: Sized,
+    {
+        Map { stream: self, op }
+    }
+
+    /// Calls `op` with a reference to each item as it passes by.
+    ///
+    /// ```rust
+    /// use moro::Stream;
+    ///
+    /// # futures::executor::block_on(async {
+    /// let mut seen = vec![];
+    /// let count = moro::source::range(0..3).inspect(|n| seen.push(*n)).count().await;
+    /// assert_eq!(count, 3);
+    /// assert_eq!(seen, [0, 1, 2]);
+    /// # });
+    /// ```
+    fn inspect(self, op: impl FnMut(&Self::Item)) -> impl Stream<Item = Self::Item>
+    where
+        Self: Sized,
+    {
+        Inspect { stream: self, op }
+    }
+
+    /// Produces at most the first `n` items, then stops the underlying stream.
+    ///
+    /// ```rust
+    /// use moro::Stream;
+    ///
+    /// # futures::executor::block_on(async {
+    /// let v: Vec<_> = moro::source::iter(0..).take(3).collect().await;
+    /// assert_eq!(v, [0, 1, 2]);
+    /// # });
+    /// ```
+    fn take(self, n: usize) -> impl Stream<Item = Self::Item>
+    where
+        Self: Sized,
+    {
+        Take {
+            stream: self,
+            remaining: n,
+        }
+    }
+
+    /// Skips items while `op` returns true, then produces the rest.
+    ///
+    /// ```rust
+    /// use moro::Stream;
+    ///
+    /// # futures::executor::block_on(async {
+    /// let v: Vec<_> = moro::source::iter([1, 2, 5, 1])
+    ///     .skip_while(async |n| *n < 3)
+    ///     .collect()
+    ///     .await;
+    /// assert_eq!(v, [5, 1]);
+    /// # });
+    /// ```
+    fn skip_while(self, op: impl async FnMut(&Self::Item) -> bool) -> impl Stream<Item = Self::Item>
+    where
+        Self: Sized,
+    {
+        SkipWhile {
+            stream: self,
+            op,
+            skipping: true,
+        }
+    }
+
+    /// Pairs each item with its index.
+    ///
+    /// ```rust
+    /// use moro::Stream;
+    ///
+    /// # futures::executor::block_on(async {
+    /// let v: Vec<_> = moro::source::iter(["a", "b"]).enumerate().collect().await;
+    /// assert_eq!(v, [(0, "a"), (1, "b")]);
+    /// # });
+    /// ```
+    fn enumerate(self) -> impl Stream<Item = (usize, Self::Item)>
+    where
+        Self: Sized,
+    {
+        Enumerate {
+            stream: self,
+            index: 0,
+        }
+    }
+
+    /// Pairs each item with the next item of `other`, stopping when either runs out.
+    /// `other` is an [`AsyncIterator`], since two streams that push their items cannot be
+    /// advanced in step.
+    ///
+    /// ```rust
+    /// use moro::Stream;
+    ///
+    /// # futures::executor::block_on(async {
+    /// let v: Vec<_> = moro::source::range(0..10)
+    ///     .zip_with(moro::source::iter(["a", "b"]))
+    ///     .collect()
+    ///     .await;
+    /// assert_eq!(v, [(0, "a"), (1, "b")]);
+    /// # });
+    /// ```
+    fn zip_with<J>(self, other: J) -> impl Stream<Item = (Self::Item, J::Item)>
+    where
+        Self: Sized,
+        J: AsyncIterator,
+    {
+        ZipWith {
+            stream: self,
+            other,
+        }
+    }
+
     async fn for_each(&mut self, mut op: impl async FnMut(Self::Item))
     where
         Self: Sized,
@@ -18,7 +190,318 @@ pub trait Stream: IntoAsyncIter {
         self.fold((), async |(), item| op(item).await).await
     }
 
-    async fn fold<R>(&mut self, start: R, op: impl async FnMut(R, Self::Item) -> R) -> R;
+    /// Combines the items with `op`, using the first item as the starting value. Returns
+    /// `None` if there are no items.
+    ///
+    /// ```rust
+    /// use moro::Stream;
+    ///
+    /// # futures::executor::block_on(async {
+    /// let product = moro::source::range(1..5).reduce(async |a, b| a * b).await;
+    /// assert_eq!(product, Some(24));
+    /// # });
+    /// ```
+    async fn reduce(
+        &mut self,
+        mut op: impl async FnMut(Self::Item, Self::Item) -> Self::Item,
+    ) -> Option<Self::Item>
+    where
+        Self: Sized,
+    {
+        self.fold(None, async |acc, item| match acc {
+            Some(acc) => Some(op(acc, item).await),
+            None => Some(item),
+        })
+        .await
+    }
+
+    /// Counts the items.
+    ///
+    /// ```rust
+    /// use moro::Stream;
+    ///
+    /// # futures::executor::block_on(async {
+    /// assert_eq!(moro::source::range(0..4).count().await, 4);
+    /// # });
+    /// ```
+    async fn count(&mut self) -> usize
+    where
+        Self: Sized,
+    {
+        self.fold(0, async |count, _| count + 1).await
+    }
+
+    /// Returns the smallest item, or the first of them if several are equally small.
+    ///
+    /// ```rust
+    /// use moro::Stream;
+    ///
+    /// # futures::executor::block_on(async {
+    /// assert_eq!(moro::source::iter([3, 1, 2]).min().await, Some(1));
+    /// # });
+    /// ```
+    async fn min(&mut self) -> Option<Self::Item>
+    where
+        Self: Sized,
+        Self::Item: Ord,
+    {
+        self.reduce(async |min, item| if item < min { item } else { min })
+            .await
+    }
+
+    /// Returns the largest item, or the last of them if several are equally large.
+    ///
+    /// ```rust
+    /// use moro::Stream;
+    ///
+    /// # futures::executor::block_on(async {
+    /// assert_eq!(moro::source::iter([3, 1, 2]).max().await, Some(3));
+    /// # });
+    /// ```
+    async fn max(&mut self) -> Option<Self::Item>
+    where
+        Self: Sized,
+        Self::Item: Ord,
+    {
+        self.reduce(async |max, item| if item >= max { item } else { max })
+            .await
+    }
+
+    /// True if `op` returns true for any item. Stops at the first such item.
+    ///
+    /// ```rust
+    /// use moro::Stream;
+    ///
+    /// # futures::executor::block_on(async {
+    /// assert!(moro::source::iter(0..).any(async |n| n > 2).await);
+    /// # });
+    /// ```
+    async fn any(&mut self, mut op: impl async FnMut(Self::Item) -> bool) -> bool
+    where
+        Self: Sized,
+    {
+        self.try_fold(false, async |_, item| {
+            if op(item).await {
+                ControlFlow::Break(true)
+            } else {
+                ControlFlow::Continue(false)
+            }
+        })
+        .await
+        .is_break()
+    }
+
+    /// True if `op` returns true for every item. Stops at the first item for which it
+    /// returns false.
+    ///
+    /// ```rust
+    /// use moro::Stream;
+    ///
+    /// # futures::executor::block_on(async {
+    /// assert!(!moro::source::iter(0..).all(async |n| n < 2).await);
+    /// # });
+    /// ```
+    async fn all(&mut self, mut op: impl async FnMut(Self::Item) -> bool) -> bool
+    where
+        Self: Sized,
+    {
+        self.try_fold(true, async |_, item| {
+            if op(item).await {
+                ControlFlow::Continue(true)
+            } else {
+                ControlFlow::Break(false)
+            }
+        })
+        .await
+        .is_continue()
+    }
+
+    /// Collects the items into a collection.
+    ///
+    /// ```rust
+    /// use moro::Stream;
+    ///
+    /// # futures::executor::block_on(async {
+    /// let set: std::collections::BTreeSet<_> = moro::source::iter([2, 1, 2]).collect().await;
+    /// assert_eq!(set.into_iter().collect::<Vec<_>>(), [1, 2]);
+    /// # });
+    /// ```
+    async fn collect<C>(&mut self) -> C
+    where
+        Self: Sized,
+        C: Default + Extend<Self::Item>,
+    {
+        self.fold(C::default(), async |mut collection, item| {
+            collection.extend(Some(item));
+            collection
+        })
+        .await
+    }
+
+    /// Like [`for_each`](Self::for_each), but the future returned by `op` for each item
+    /// is spawned as a job in `scope`, with at most `limit` of them running at once.
+    /// Completes once every item has been processed.
+    ///
+    /// # Panics
+    ///
+    /// If `limit` is zero.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// #![feature(async_trait_bounds)]
+    /// use moro::Stream;
+    ///
+    /// # futures::executor::block_on(async {
+    /// let mut numbers = moro::source::range(1..5);
+    /// let total = &std::cell::Cell::new(0);
+    /// moro::infallible_scope!(|scope| {
+    ///     numbers
+    ///         .for_each_concurrent(scope, 2, |n| async move { total.set(total.get() + n) })
+    ///         .await;
+    /// })
+    /// .await;
+    /// assert_eq!(total.get(), 10);
+    /// # });
+    /// ```
+    async fn for_each_concurrent<'scope, F>(
+        &mut self,
+        scope: &'scope Scope<'scope, '_>,
+        limit: usize,
+        op: impl FnMut(Self::Item) -> F,
+    ) where
+        Self: Sized,
+        F: Future<Output = ()> + 'scope,
+    {
+        concurrent::for_each_concurrent(self, scope, limit, op).await
+    }
+
+    /// Maps each item with `op`, spawning the future it returns as a job in `scope`, with
+    /// at most `limit` of them running at once. The results are produced in the given
+    /// [`Order`].
+    ///
+    /// # Panics
+    ///
+    /// If `limit` is zero.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// #![feature(async_trait_bounds)]
+    /// use moro::{Order, Stream};
+    ///
+    /// # futures::executor::block_on(async {
+    /// let factor = 10;
+    /// let result = moro::infallible_scope!(|scope| {
+    ///     moro::source::range(1..5)
+    ///         .map_concurrent(scope, 2, Order::Ordered, |n| async move { n * factor })
+    ///         .fold(vec![], async |mut v, n| {
+    ///             v.push(n);
+    ///             v
+    ///         })
+    ///         .await
+    /// })
+    /// .await;
+    /// assert_eq!(result, [10, 20, 30, 40]);
+    /// # });
+    /// ```
+    fn map_concurrent<'scope, F>(
+        self,
+        scope: &'scope Scope<'scope, '_>,
+        limit: usize,
+        order: Order,
+        op: impl FnMut(Self::Item) -> F,
+    ) -> impl Stream<Item = F::Output>
+    where
+        Self: Sized,
+        F: Future + 'scope,
+    {
+        MapConcurrent::new(self, scope, limit, order, op)
+    }
+
+    /// Merges this stream with `other`: see [`select_all`](crate::select_all).
+    ///
+    /// ```rust
+    /// use moro::Stream;
+    ///
+    /// # futures::executor::block_on(async {
+    /// let result: Vec<_> = moro::infallible_scope!(|scope| {
+    ///     moro::source::iter([1, 2, 3])
+    ///         .merge(scope, moro::source::iter([10, 20]))
+    ///         .collect()
+    ///         .await
+    /// })
+    /// .await;
+    /// assert_eq!(result, [1, 10, 2, 20, 3]);
+    /// # });
+    /// ```
+    fn merge<'scope, S>(
+        self,
+        scope: &'scope Scope<'scope, '_>,
+        other: S,
+    ) -> impl Stream<Item = Self::Item> + 'scope
+    where
+        Self: Sized + 'scope,
+        S: Stream<Item = Self::Item> + 'scope,
+    {
+        let mut merge = Merge::new();
+        merge.add(scope, self);
+        merge.add(scope, other);
+        merge
+    }
+
+    /// Converts this stream into a [`futures::Stream`], by way of
+    /// [`into_async_iter`](IntoAsyncIter::into_async_iter).
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// #![feature(async_trait_bounds)]
+    /// use futures::StreamExt;
+    /// use moro::Stream;
+    ///
+    /// # futures::executor::block_on(async {
+    /// let result = moro::infallible_scope!(|scope| {
+    ///     moro::source::range(0..6)
+    ///         .filter(async |n| n % 2 == 0)
+    ///         .into_futures_stream(scope)
+    ///         .collect::<Vec<_>>()
+    ///         .await
+    /// })
+    /// .await;
+    /// assert_eq!(result, [0, 2, 4]);
+    /// # });
+    /// ```
+    fn into_futures_stream(self, scope: &Scope<'_, '_>) -> impl futures::Stream<Item = Self::Item>
+    where
+        Self: Sized,
+    {
+        self.into_async_iter(scope).into_futures_stream()
+    }
+
+    /// Combines the items into a single value with `op`, starting from `start`.
+    async fn fold<R>(&mut self, start: R, mut op: impl async FnMut(R, Self::Item) -> R) -> R {
+        let flow = self
+            .try_fold(start, async |acc, item| {
+                ControlFlow::Continue(op(acc, item).await)
+            })
+            .await;
+        match flow {
+            ControlFlow::Continue(acc) | ControlFlow::Break(acc) => acc,
+        }
+    }
+
+    /// Like [`fold`](Self::fold), but stops as soon as `op` returns
+    /// [`ControlFlow::Break`], without producing any more items. Returns `Break` if `op`
+    /// did, and `Continue` if the stream ran out of items.
+    ///
+    /// This is the method that implementations of `Stream` provide; everything else is
+    /// built on it.
+    async fn try_fold<R>(
+        &mut self,
+        start: R,
+        op: impl async FnMut(R, Self::Item) -> ControlFlow<R, R>,
+    ) -> ControlFlow<R, R>;
 }
 
 struct Filter<S, O>
@@ -35,13 +518,17 @@ where
     S: Stream,
     O: async FnMut(&S::Item) -> bool,
 {
-    async fn fold<R>(&mut self, start: R, mut op: impl async FnMut(R, Self::Item) -> R) -> R {
+    async fn try_fold<R>(
+        &mut self,
+        start: R,
+        mut op: impl async FnMut(R, Self::Item) -> ControlFlow<R, R>,
+    ) -> ControlFlow<R, R> {
         self.stream
-            .fold(start, async |acc, item| {
+            .try_fold(start, async |acc, item| {
                 if (self.filter_op)(&item).await {
                     op(acc, item).await
                 } else {
-                    acc
+                    ControlFlow::Continue(acc)
                 }
             })
             .await
@@ -55,11 +542,249 @@ where
 {
     type Item = S::Item;
 
-    fn into_async_iter<R: Send>(
-        self,
-        scope: &Scope<'_, '_, R>,
-    ) -> impl AsyncIterator<Item = Self::Item> {
+    fn into_async_iter(self, scope: &Scope<'_, '_>) -> impl AsyncIterator<Item = Self::Item> {
         let iter = self.stream.into_async_iter(scope);
         iter.filter(self.filter_op)
     }
 }
+
+struct Map<S, O> {
+    stream: S,
+    op: O,
+}
+
+impl<S, O, U> Stream for Map<S, O>
+where
+    S: Stream,
+    O: FnMut(S::Item) -> U,
+{
+    async fn try_fold<R>(
+        &mut self,
+        start: R,
+        mut op: impl async FnMut(R, Self::Item) -> ControlFlow<R, R>,
+    ) -> ControlFlow<R, R> {
+        self.stream
+            .try_fold(start, async |acc, item| op(acc, (self.op)(item)).await)
+            .await
+    }
+}
+
+impl<S, O, U> IntoAsyncIter for Map<S, O>
+where
+    S: Stream,
+    O: FnMut(S::Item) -> U,
+{
+    type Item = U;
+
+    fn into_async_iter(self, scope: &Scope<'_, '_>) -> impl AsyncIterator<Item = Self::Item> {
+        let iter = self.stream.into_async_iter(scope);
+        iter.map(self.op)
+    }
+}
+
+struct Inspect<S, O> {
+    stream: S,
+    op: O,
+}
+
+impl<S, O> Stream for Inspect<S, O>
+where
+    S: Stream,
+    O: FnMut(&S::Item),
+{
+    async fn try_fold<R>(
+        &mut self,
+        start: R,
+        mut op: impl async FnMut(R, Self::Item) -> ControlFlow<R, R>,
+    ) -> ControlFlow<R, R> {
+        self.stream
+            .try_fold(start, async |acc, item| {
+                (self.op)(&item);
+                op(acc, item).await
+            })
+            .await
+    }
+}
+
+impl<S, O> IntoAsyncIter for Inspect<S, O>
+where
+    S: Stream,
+    O: FnMut(&S::Item),
+{
+    type Item = S::Item;
+
+    fn into_async_iter(self, scope: &Scope<'_, '_>) -> impl AsyncIterator<Item = Self::Item> {
+        let iter = self.stream.into_async_iter(scope);
+        let mut op = self.op;
+        iter.map(move |item| {
+            op(&item);
+            item
+        })
+    }
+}
+
+struct Take<S> {
+    stream: S,
+    remaining: usize,
+}
+
+impl<S: Stream> Stream for Take<S> {
+    async fn try_fold<R>(
+        &mut self,
+        start: R,
+        mut op: impl async FnMut(R, Self::Item) -> ControlFlow<R, R>,
+    ) -> ControlFlow<R, R> {
+        if self.remaining == 0 {
+            return ControlFlow::Continue(start);
+        }
+        let mut taken_all = false;
+        let flow = self
+            .stream
+            .try_fold(start, async |acc, item| {
+                self.remaining -= 1;
+                let acc = op(acc, item).await?;
+                if self.remaining == 0 {
+                    taken_all = true;
+                    return ControlFlow::Break(acc);
+                }
+                ControlFlow::Continue(acc)
+            })
+            .await;
+        match flow {
+            // Running out of items to take is the end of this stream, not an early stop.
+            ControlFlow::Break(acc) if taken_all => ControlFlow::Continue(acc),
+            flow => flow,
+        }
+    }
+}
+
+impl<S: Stream> IntoAsyncIter for Take<S> {
+    type Item = S::Item;
+
+    fn into_async_iter(self, scope: &Scope<'_, '_>) -> impl AsyncIterator<Item = Self::Item> {
+        let iter = self.stream.into_async_iter(scope);
+        iter.take(self.remaining)
+    }
+}
+
+struct SkipWhile<S, O> {
+    stream: S,
+    op: O,
+    skipping: bool,
+}
+
+impl<S, O> Stream for SkipWhile<S, O>
+where
+    S: Stream,
+    O: async FnMut(&S::Item) -> bool,
+{
+    async fn try_fold<R>(
+        &mut self,
+        start: R,
+        mut op: impl async FnMut(R, Self::Item) -> ControlFlow<R, R>,
+    ) -> ControlFlow<R, R> {
+        self.stream
+            .try_fold(start, async |acc, item| {
+                if self.skipping && (self.op)(&item).await {
+                    return ControlFlow::Continue(acc);
+                }
+                self.skipping = false;
+                op(acc, item).await
+            })
+            .await
+    }
+}
+
+impl<S, O> IntoAsyncIter for SkipWhile<S, O>
+where
+    S: Stream,
+    O: async FnMut(&S::Item) -> bool,
+{
+    type Item = S::Item;
+
+    fn into_async_iter(self, scope: &Scope<'_, '_>) -> impl AsyncIterator<Item = Self::Item> {
+        let iter = self.stream.into_async_iter(scope);
+        let mut op = self.op;
+        let mut skipping = self.skipping;
+        iter.filter(async move |item| {
+            skipping = skipping && op(item).await;
+            !skipping
+        })
+    }
+}
+
+struct Enumerate<S> {
+    stream: S,
+    index: usize,
+}
+
+impl<S: Stream> Stream for Enumerate<S> {
+    async fn try_fold<R>(
+        &mut self,
+        start: R,
+        mut op: impl async FnMut(R, Self::Item) -> ControlFlow<R, R>,
+    ) -> ControlFlow<R, R> {
+        self.stream
+            .try_fold(start, async |acc, item| {
+                let index = self.index;
+                self.index += 1;
+                op(acc, (index, item)).await
+            })
+            .await
+    }
+}
+
+impl<S: Stream> IntoAsyncIter for Enumerate<S> {
+    type Item = (usize, S::Item);
+
+    fn into_async_iter(self, scope: &Scope<'_, '_>) -> impl AsyncIterator<Item = Self::Item> {
+        let iter = self.stream.into_async_iter(scope);
+        iter.enumerate()
+    }
+}
+
+struct ZipWith<S, J> {
+    stream: S,
+    other: J,
+}
+
+impl<S, J> Stream for ZipWith<S, J>
+where
+    S: Stream,
+    J: AsyncIterator,
+{
+    async fn try_fold<R>(
+        &mut self,
+        start: R,
+        mut op: impl async FnMut(R, Self::Item) -> ControlFlow<R, R>,
+    ) -> ControlFlow<R, R> {
+        let mut other_done = false;
+        let flow = self
+            .stream
+            .try_fold(start, async |acc, item| match self.other.next().await {
+                Some(other) => op(acc, (item, other)).await,
+                None => {
+                    other_done = true;
+                    ControlFlow::Break(acc)
+                }
+            })
+            .await;
+        match flow {
+            ControlFlow::Break(acc) if other_done => ControlFlow::Continue(acc),
+            flow => flow,
+        }
+    }
+}
+
+impl<S, J> IntoAsyncIter for ZipWith<S, J>
+where
+    S: Stream,
+    J: AsyncIterator,
+{
+    type Item = (S::Item, J::Item);
+
+    fn into_async_iter(self, scope: &Scope<'_, '_>) -> impl AsyncIterator<Item = Self::Item> {
+        let iter = self.stream.into_async_iter(scope);
+        iter.zip(self.other)
+    }
+}
@@ -0,0 +1,366 @@
+use std::{
+    any::Any,
+    collections::VecDeque,
+    fmt,
+    task::Poll,
+    time::{Duration, Instant},
+};
+
+use futures::{future::LocalBoxFuture, Future};
+
+use crate::{Aborted, Scope, Spawned};
+
+/// Which children a [`Supervisor`] restarts when one of them fails.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum Strategy {
+    /// Restart only the child that failed.
+    #[default]
+    OneForOne,
+
+    /// Abort all of the other children and restart all of them, except those that have
+    /// already completed successfully.
+    OneForAll,
+
+    /// Abort the children added after the one that failed, and restart it and them
+    /// (except those that have already completed successfully).
+    RestForOne,
+}
+
+/// How long a [`Supervisor`] waits before restarting a child. The delay is measured by
+/// the scope's [timer](crate::ScopeBody::with_timer).
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Backoff {
+    initial: Duration,
+    max: Duration,
+}
+
+impl Backoff {
+    /// Wait `delay` before every restart.
+    pub fn fixed(delay: Duration) -> Self {
+        Self {
+            initial: delay,
+            max: delay,
+        }
+    }
+
+    /// Wait `initial` before a child's first restart, doubling the delay for each further
+    /// restart of that child, up to `max`.
+    pub fn exponential(initial: Duration, max: Duration) -> Self {
+        Self { initial, max }
+    }
+
+    /// The delay before restarting a child that has already been restarted `restarts` times.
+    fn delay(&self, restarts: u32) -> Duration {
+        let factor = 2u32.saturating_pow(restarts);
+        self.initial.saturating_mul(factor).min(self.max)
+    }
+}
+
+/// Why a supervised child failed.
+pub enum ChildFailure<E> {
+    /// The child returned an error.
+    Error(E),
+
+    /// The child panicked, with this payload.
+    Panic(Box<dyn Any + Send>),
+}
+
+impl<E: fmt::Debug> fmt::Debug for ChildFailure<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChildFailure::Error(e) => f.debug_tuple("Error").field(e).finish(),
+            ChildFailure::Panic(_) => f.debug_tuple("Panic").finish_non_exhaustive(),
+        }
+    }
+}
+
+/// Returned by [`Supervisor::run`] when children fail more often than the restart
+/// intensity allows.
+#[derive(Debug)]
+pub struct SupervisorError<E> {
+    /// The index of the child whose failure exceeded the limit, in the order the
+    /// children were added.
+    pub child: usize,
+
+    /// That child's failure.
+    pub failure: ChildFailure<E>,
+}
+
+impl<E: fmt::Display> fmt::Display for SupervisorError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.failure {
+            ChildFailure::Error(e) => write!(f, "supervised child {} failed: {e}", self.child),
+            ChildFailure::Panic(_) => write!(f, "supervised child {} panicked", self.child),
+        }
+    }
+}
+
+impl<E: fmt::Debug + fmt::Display> std::error::Error for SupervisorError<E> {}
+
+/// Creates the future for one run of a supervised child.
+type ChildFactory<'scope, E> = Box<dyn FnMut() -> LocalBoxFuture<'scope, Result<(), E>> + 'scope>;
+
+type ChildHandle<'scope, E> = Spawned<'scope, std::thread::Result<Result<(), E>>>;
+
+struct Child<'scope, E> {
+    factory: ChildFactory<'scope, E>,
+
+    /// The current run of the child, unless it has ended.
+    running: Option<ChildHandle<'scope, E>>,
+
+    /// The number of times the child has been restarted.
+    restarts: u32,
+}
+
+/// Keeps long-lived jobs ("children") running in a scope, restarting them when they fail
+/// (return an error or panic), in the manner of an Erlang supervisor. Created with
+/// [`Scope::supervisor`].
+///
+/// A child that completes successfully is not restarted; [`run`](Self::run) completes
+/// once every child has. If children fail more than `max_restarts` times within `period`
+/// (see [`with_intensity`](Self::with_intensity)), the supervisor gives up: it aborts
+/// the remaining children and `run` returns the failure. To terminate the scope in that
+/// case, spawn `run` and use [`or_cancel`](crate::Spawned::or_cancel).
+///
+/// # Examples
+///
+/// ```rust
+/// # futures::executor::block_on(async {
+/// let attempts = std::cell::Cell::new(0);
+/// let result: Result<(), moro::SupervisorError<&str>> = moro::async_scope!(|scope| {
+///     scope
+///         .supervisor()
+///         .child(|| async {
+///             attempts.set(attempts.get() + 1);
+///             if attempts.get() < 3 {
+///                 return Err("flaky");
+///             }
+///             Ok(())
+///         })
+///         .run()
+///         .await
+/// })
+/// .await;
+/// assert!(result.is_ok());
+/// assert_eq!(attempts.get(), 3);
+/// # });
+/// ```
+///
+/// Escalating to the enclosing scope:
+///
+/// ```rust
+/// # futures::executor::block_on(async {
+/// let result: Result<(), String> = moro::async_scope!(|scope| {
+///     let supervisor = scope
+///         .supervisor()
+///         .with_intensity(2, std::time::Duration::from_secs(60))
+///         .child(|| async { Err("broken") });
+///     scope
+///         .spawn(async { supervisor.run().await.map_err(|e| e.to_string()) })
+///         .or_cancel(scope)
+///         .await;
+///     Ok(())
+/// })
+/// .await;
+/// assert_eq!(result.unwrap_err(), "supervised child 0 failed: broken");
+/// # });
+/// ```
+pub struct Supervisor<'scope, 'env: 'scope, E> {
+    scope: &'scope Scope<'scope, 'env>,
+    strategy: Strategy,
+    max_restarts: usize,
+    period: Duration,
+    backoff: Option<Backoff>,
+    children: Vec<Child<'scope, E>>,
+}
+
+impl<'scope, 'env, E> Supervisor<'scope, 'env, E>
+where
+    E: 'scope,
+{
+    pub(crate) fn new(scope: &'scope Scope<'scope, 'env>) -> Self {
+        Self {
+            scope,
+            strategy: Strategy::default(),
+            max_restarts: 3,
+            period: Duration::from_secs(5),
+            backoff: None,
+            children: vec![],
+        }
+    }
+
+    /// Sets which children are restarted when one fails. The default is
+    /// [`Strategy::OneForOne`].
+    pub fn with_strategy(mut self, strategy: Strategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    /// Gives up once there have been more than `max_restarts` failures within `period`.
+    /// The default is 3 failures within 5 seconds. Like the backoff, the period is
+    /// measured by the scope's [timer](crate::ScopeBody::with_timer).
+    ///
+    /// # Examples
+    ///
+    /// A timer whose clock jumps ahead instead of sleeping. With a second of backoff, the
+    /// child never fails twice within half a second, however quickly it really fails:
+    ///
+    /// ```rust
+    /// # futures::executor::block_on(async {
+    /// use std::{cell::Cell, rc::Rc, time::{Duration, Instant}};
+    /// use futures::future::LocalBoxFuture;
+    ///
+    /// struct Virtual(Rc<Cell<Instant>>);
+    ///
+    /// impl moro::time::Timer for Virtual {
+    ///     fn sleep(&self, duration: Duration) -> LocalBoxFuture<'static, ()> {
+    ///         self.0.set(self.0.get() + duration);
+    ///         Box::pin(async {})
+    ///     }
+    ///
+    ///     fn now(&self) -> Instant {
+    ///         self.0.get()
+    ///     }
+    /// }
+    ///
+    /// let attempts = Cell::new(0);
+    /// let result: Result<(), moro::SupervisorError<&str>> = moro::async_scope!(|scope| {
+    ///     scope
+    ///         .supervisor()
+    ///         .with_intensity(1, Duration::from_millis(500))
+    ///         .with_backoff(moro::Backoff::fixed(Duration::from_secs(1)))
+    ///         .child(|| async {
+    ///             attempts.set(attempts.get() + 1);
+    ///             if attempts.get() < 10 {
+    ///                 return Err("flaky");
+    ///             }
+    ///             Ok(())
+    ///         })
+    ///         .run()
+    ///         .await
+    /// })
+    /// .with_timer(Virtual(Rc::new(Cell::new(Instant::now()))))
+    /// .await;
+    /// assert!(result.is_ok());
+    /// # });
+    /// ```
+    pub fn with_intensity(mut self, max_restarts: usize, period: Duration) -> Self {
+        self.max_restarts = max_restarts;
+        self.period = period;
+        self
+    }
+
+    /// Waits before restarting a child. By default, children are restarted right away.
+    pub fn with_backoff(mut self, backoff: Backoff) -> Self {
+        self.backoff = Some(backoff);
+        self
+    }
+
+    /// Adds a child. `factory` is invoked to start the child, and again each time it
+    /// is restarted.
+    pub fn child<F>(mut self, mut factory: impl FnMut() -> F + 'scope) -> Self
+    where
+        F: Future<Output = Result<(), E>> + 'scope,
+    {
+        self.children.push(Child {
+            factory: Box::new(move || Box::pin(factory())),
+            running: None,
+            restarts: 0,
+        });
+        self
+    }
+
+    /// Starts the children and supervises them until they have all completed, or until
+    /// they fail too often.
+    pub async fn run(mut self) -> Result<(), SupervisorError<E>> {
+        for index in 0..self.children.len() {
+            self.start(index);
+        }
+
+        let mut failures: VecDeque<Instant> = VecDeque::new();
+        while let Some((index, output)) = self.next_exit().await {
+            let failure = match output {
+                Ok(Ok(())) => continue,
+                Ok(Err(e)) => ChildFailure::Error(e),
+                Err(payload) => ChildFailure::Panic(payload),
+            };
+
+            let now = self.scope.now();
+            failures.push_back(now);
+            while failures
+                .front()
+                .is_some_and(|&failed| now.duration_since(failed) > self.period)
+            {
+                failures.pop_front();
+            }
+            if failures.len() > self.max_restarts {
+                for child in &mut self.children {
+                    if let Some(handle) = child.running.take() {
+                        handle.abort();
+                    }
+                }
+                return Err(SupervisorError {
+                    child: index,
+                    failure,
+                });
+            }
+
+            let range = match self.strategy {
+                Strategy::OneForOne => index..index + 1,
+                Strategy::OneForAll => 0..self.children.len(),
+                Strategy::RestForOne => index..self.children.len(),
+            };
+            // Children that already completed successfully stay completed.
+            let restart: Vec<usize> = range
+                .filter(|&i| i == index || self.children[i].running.is_some())
+                .collect();
+            for &i in &restart {
+                if let Some(handle) = self.children[i].running.take() {
+                    handle.abort();
+                }
+            }
+            if let Some(backoff) = self.backoff {
+                let delay = backoff.delay(self.children[index].restarts);
+                self.scope.timer().sleep(delay).await;
+            }
+            for i in restart {
+                self.children[i].restarts += 1;
+                self.start(i);
+            }
+        }
+        Ok(())
+    }
+
+    fn start(&mut self, index: usize) {
+        let child = &mut self.children[index];
+        child.running = Some(self.scope.spawn_catch_unwind((child.factory)()));
+    }
+
+    /// Waits for a running child to exit, returning its index and output. Returns `None`
+    /// if no children are running.
+    async fn next_exit(&mut self) -> Option<(usize, std::thread::Result<Result<(), E>>)> {
+        futures::future::poll_fn(|cx| {
+            let mut running = false;
+            for (index, child) in self.children.iter_mut().enumerate() {
+                let Some(handle) = &child.running else {
+                    continue;
+                };
+                match handle.poll_result(cx) {
+                    Poll::Ready(Ok(output)) => {
+                        child.running = None;
+                        return Poll::Ready(Some((index, output)));
+                    }
+                    // A child that was aborted by someone else stays stopped.
+                    Poll::Ready(Err(Aborted)) => child.running = None,
+                    Poll::Pending => running = true,
+                }
+            }
+            if running {
+                Poll::Pending
+            } else {
+                Poll::Ready(None)
+            }
+        })
+        .await
+    }
+}
@@ -0,0 +1,68 @@
+//! Deadlines for scopes and jobs.
+//!
+//! moro does not depend on any particular executor, so deadlines are measured by a
+//! [`Timer`] that you supply with [`ScopeBody::with_timer`][crate::ScopeBody::with_timer].
+//! With the `tokio` cargo feature enabled, [`TokioTimer`] is provided and used by default.
+
+use std::time::{Duration, Instant};
+
+use futures::future::LocalBoxFuture;
+
+/// A clock and a source of sleeps, used to implement deadlines and the restart windows of
+/// [supervisors](crate::Supervisor).
+///
+/// # Examples
+///
+/// A timer backed by tokio (this is what [`TokioTimer`] does):
+///
+/// ```rust
+/// use std::time::Duration;
+/// use futures::future::LocalBoxFuture;
+///
+/// struct MyTimer;
+///
+/// impl moro::time::Timer for MyTimer {
+///     fn sleep(&self, duration: Duration) -> LocalBoxFuture<'static, ()> {
+///         Box::pin(tokio::time::sleep(duration))
+///     }
+/// }
+/// ```
+pub trait Timer {
+    /// Returns a future that completes once `duration` has elapsed.
+    fn sleep(&self, duration: Duration) -> LocalBoxFuture<'static, ()>;
+
+    /// The current time, on the clock that [`sleep`](Self::sleep) measures. The default
+    /// is [`Instant::now`].
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+/// A [`Timer`] that uses [`tokio::time::sleep`].
+#[cfg(feature = "tokio")]
+#[derive(Copy, Clone, Debug, Default)]
+pub struct TokioTimer;
+
+#[cfg(feature = "tokio")]
+impl Timer for TokioTimer {
+    fn sleep(&self, duration: Duration) -> LocalBoxFuture<'static, ()> {
+        Box::pin(tokio::time::sleep(duration))
+    }
+
+    /// Tokio's clock, which stands still while tokio's time is paused.
+    fn now(&self) -> Instant {
+        tokio::time::Instant::now().into_std()
+    }
+}
+
+/// Error returned by the `fail_after` methods when the deadline passes first.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Elapsed;
+
+impl std::fmt::Display for Elapsed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "deadline has elapsed")
+    }
+}
+
+impl std::error::Error for Elapsed {}
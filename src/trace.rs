@@ -0,0 +1,76 @@
+//! Spans and events for the `tracing` cargo feature. Without the feature, these are
+//! no-ops.
+
+use std::panic::Location;
+
+use crate::registry::{Kind, Status};
+
+/// The span of a job or scope.
+#[cfg(feature = "tracing")]
+pub(crate) struct Span(tracing::Span);
+
+#[cfg(not(feature = "tracing"))]
+pub(crate) struct Span;
+
+#[cfg(feature = "tracing")]
+impl Span {
+    /// Creates the span of a job (or, for [`Kind::Body`], a scope), as a child of the
+    /// current span: that of the job or scope that spawned it.
+    pub(crate) fn new(
+        kind: Kind,
+        location: Option<&'static Location<'static>>,
+        name: Option<&str>,
+    ) -> Self {
+        let location = location.map(tracing::field::display);
+        let span = match kind {
+            Kind::Body => tracing::info_span!(target: "moro", "scope", location),
+            Kind::Job(id) => tracing::info_span!(target: "moro", "job", id, name, location),
+            Kind::Finalizers(id) => tracing::info_span!(target: "moro", "finalizers", id),
+        };
+        if !matches!(kind, Kind::Body) {
+            tracing::debug!(target: "moro", parent: &span, "spawned");
+        }
+        Self(span)
+    }
+
+    /// Runs `f` (a poll) inside the span.
+    pub(crate) fn in_scope<R>(&self, f: impl FnOnce() -> R) -> R {
+        self.0.in_scope(f)
+    }
+
+    /// Records that the job has finished.
+    pub(crate) fn finished(&self, status: Status) {
+        let span = &self.0;
+        match status {
+            Status::Done => tracing::debug!(target: "moro", parent: span, "completed"),
+            Status::Panicked => tracing::debug!(target: "moro", parent: span, "panicked"),
+            Status::Aborted => tracing::debug!(target: "moro", parent: span, "cancelled"),
+            Status::Failed => tracing::debug!(target: "moro", parent: span, "failed"),
+            Status::Enqueued | Status::Pending | Status::Polling => {}
+        }
+    }
+
+    /// Records that the scope has been terminated.
+    pub(crate) fn terminated(&self) {
+        tracing::debug!(target: "moro", parent: &self.0, "terminated");
+    }
+}
+
+#[cfg(not(feature = "tracing"))]
+impl Span {
+    pub(crate) fn new(
+        _kind: Kind,
+        _location: Option<&'static Location<'static>>,
+        _name: Option<&str>,
+    ) -> Self {
+        Self
+    }
+
+    pub(crate) fn in_scope<R>(&self, f: impl FnOnce() -> R) -> R {
+        f()
+    }
+
+    pub(crate) fn finished(&self, _status: Status) {}
+
+    pub(crate) fn terminated(&self) {}
+}
@@ -0,0 +1,201 @@
+//! Checks the spans and events recorded with the `tracing` cargo feature.
+#![cfg(feature = "tracing")]
+
+use std::{
+    fmt,
+    sync::{Arc, Mutex},
+    task::Poll,
+};
+
+use tracing::{
+    field::{Field, Visit},
+    span::{Attributes, Id},
+    Event, Subscriber,
+};
+use tracing_subscriber::{layer::Context, prelude::*, registry::LookupSpan, Layer};
+
+/// A span as seen by [`Capture`]: its name, plus the `id` field for jobs.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Name(String);
+
+/// What was recorded, in order.
+#[derive(Default)]
+struct Log {
+    /// Each span when it is created, with its parent.
+    spans: Vec<(Name, Option<Name>)>,
+
+    /// Each time a span is entered.
+    enters: Vec<Name>,
+
+    /// Each event, with the span it belongs to.
+    events: Vec<(Name, String)>,
+}
+
+#[derive(Clone, Default)]
+struct Capture(Arc<Mutex<Log>>);
+
+/// Collects the `id` field of a span or the message of an event.
+#[derive(Default)]
+struct Fields {
+    id: Option<u64>,
+    message: String,
+}
+
+impl Visit for Fields {
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        if field.name() == "id" {
+            self.id = Some(value);
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == "message" {
+            self.message = format!("{value:?}");
+        }
+    }
+}
+
+impl<S> Layer<S> for Capture
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let mut fields = Fields::default();
+        attrs.record(&mut fields);
+        let span = ctx.span(id).unwrap();
+        let name = match fields.id {
+            Some(job) => Name(format!("{} {job}", span.name())),
+            None => Name(span.name().to_string()),
+        };
+        span.extensions_mut().insert(name.clone());
+        let parent = span
+            .parent()
+            .map(|parent| parent.extensions().get::<Name>().unwrap().clone());
+        self.0.lock().unwrap().spans.push((name, parent));
+    }
+
+    fn on_enter(&self, id: &Id, ctx: Context<'_, S>) {
+        let name = ctx
+            .span(id)
+            .unwrap()
+            .extensions()
+            .get::<Name>()
+            .unwrap()
+            .clone();
+        self.0.lock().unwrap().enters.push(name);
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        let mut fields = Fields::default();
+        event.record(&mut fields);
+        let span = ctx.event_span(event).unwrap();
+        let name = span.extensions().get::<Name>().unwrap().clone();
+        self.0.lock().unwrap().events.push((name, fields.message));
+    }
+}
+
+/// Pending once, then ready.
+async fn yield_once() {
+    let mut yielded = false;
+    futures::future::poll_fn(|cx| {
+        if yielded {
+            return Poll::Ready(());
+        }
+        yielded = true;
+        cx.waker().wake_by_ref();
+        Poll::Pending
+    })
+    .await
+}
+
+fn name(name: &str) -> Name {
+    Name(name.to_string())
+}
+
+/// Runs `f` with [`Capture`] as the subscriber, returning its log.
+fn capture(f: impl FnOnce()) -> Log {
+    let capture = Capture::default();
+    let subscriber = tracing_subscriber::registry().with(capture.clone());
+    tracing::subscriber::with_default(subscriber, f);
+    let log = std::mem::take(&mut *capture.0.lock().unwrap());
+    log
+}
+
+/// The events of `log` as `(span, message)` pairs.
+fn events(log: &Log) -> Vec<(Name, &str)> {
+    log.events
+        .iter()
+        .map(|(span, message)| (span.clone(), message.as_str()))
+        .collect()
+}
+
+#[test]
+fn job_spawned_by_job() {
+    let log = capture(|| {
+        let result = futures::executor::block_on(moro::async_scope!(|scope| {
+            let outer = scope.spawn(async {
+                let inner = scope.spawn(async {
+                    yield_once().await;
+                    22
+                });
+                inner.output().await
+            });
+            assert_eq!(outer.await, Ok(22));
+
+            scope.spawn(futures::future::pending::<()>()).abort();
+            scope.terminate("stop").await
+        }));
+        assert_eq!(result, "stop");
+    });
+
+    // The inner job's span is a child of the span of the job that spawned it.
+    assert_eq!(
+        log.spans,
+        [
+            (name("scope"), None),
+            (name("job 0"), Some(name("scope"))),
+            (name("job 1"), Some(name("job 0"))),
+            (name("job 2"), Some(name("scope"))),
+        ]
+    );
+
+    assert_eq!(
+        events(&log),
+        [
+            (name("job 0"), "spawned"),
+            (name("job 1"), "spawned"),
+            (name("job 1"), "completed"),
+            (name("job 0"), "completed"),
+            (name("job 2"), "spawned"),
+            (name("job 2"), "cancelled"),
+            (name("scope"), "terminated"),
+        ]
+    );
+
+    // The inner job is polled twice, since it yields once, and its span is entered
+    // for each poll.
+    let inner_enters = log.enters.iter().filter(|span| **span == name("job 1"));
+    assert_eq!(inner_enters.count(), 2);
+}
+
+#[test]
+fn job_cancelled_while_polled() {
+    let log = capture(|| {
+        futures::executor::block_on(moro::infallible_scope!(|scope| {
+            let region = scope.cancel_scope();
+            let job = region.spawn({
+                let region = region.clone();
+                async move {
+                    region.cancel();
+                    futures::future::pending::<()>().await
+                }
+            });
+            assert!(job.await.is_err());
+        }));
+    });
+
+    assert_eq!(
+        events(&log),
+        [(name("job 0"), "spawned"), (name("job 0"), "cancelled")]
+    );
+}